@@ -0,0 +1,181 @@
+//! The derive macro for [`ExtractKey`](https://docs.rs/extract_map/latest/extract_map/trait.ExtractKey.html).
+//!
+//! This should be used via the `derive` feature of `extract_map`, instead of directly.
+#![warn(clippy::pedantic, rust_2018_idioms, missing_docs)]
+
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use syn::{spanned::Spanned, Data, DeriveInput, Error, Field, Fields, Index, Member};
+
+/// Derives `ExtractKey<K>` for a struct, where `K` is the type of the field marked with `#[extract_key]`.
+///
+/// Multiple fields may be marked, as long as each has a different type, which generates an `ExtractKey`
+/// implementation for each key type.
+///
+/// # Examples
+/// ```
+/// use extract_map::{ExtractKey, ExtractMap};
+///
+/// #[derive(ExtractKey)]
+/// struct User {
+///     #[extract_key]
+///     id: u64,
+///     #[extract_key]
+///     name: String,
+/// }
+///
+/// let mut by_id = ExtractMap::<u64, User>::new();
+/// by_id.insert(User { id: 1, name: String::from("Elliott") });
+///
+/// assert_eq!(ExtractKey::<String>::extract_key(by_id.get(&1).unwrap()), "Elliott");
+/// ```
+///
+/// Generic structs and tuple structs are also supported.
+/// ```
+/// use extract_map::{ExtractKey, ExtractMap};
+///
+/// #[derive(ExtractKey)]
+/// struct Tagged<K, T>(#[extract_key] K, T);
+///
+/// let map: ExtractMap<&str, Tagged<&str, u32>> = [Tagged("one", 1), Tagged("two", 2)].into_iter().collect();
+///
+/// assert_eq!(map.get("two").unwrap().1, 2);
+/// ```
+///
+/// # Errors
+/// A struct with no marked fields fails to compile.
+/// ```compile_fail
+/// #[derive(extract_map::ExtractKey)]
+/// struct User {
+///     id: u64,
+/// }
+/// ```
+///
+/// As does a struct with multiple marked fields of the same type, as the key would be ambiguous.
+/// ```compile_fail
+/// #[derive(extract_map::ExtractKey)]
+/// struct User {
+///     #[extract_key]
+///     id: u64,
+///     #[extract_key]
+///     age: u64,
+/// }
+/// ```
+#[proc_macro_derive(ExtractKey, attributes(extract_key))]
+pub fn derive_extract_key(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        Data::Enum(data) => {
+            let msg = "`ExtractKey` can only be derived for structs";
+            return Err(Error::new(data.enum_token.span, msg));
+        }
+        Data::Union(data) => {
+            let msg = "`ExtractKey` can only be derived for structs";
+            return Err(Error::new(data.union_token.span, msg));
+        }
+    };
+
+    let key_fields = marked_fields(fields)?;
+    if key_fields.is_empty() {
+        let msg = "no field is marked with `#[extract_key]`";
+        return Err(Error::new(input.ident.span(), msg));
+    }
+
+    check_distinct_key_types(&key_fields)?;
+
+    let name = &input.ident;
+    let impls = key_fields.iter().map(|(member, field)| {
+        let key_ty = &field.ty;
+
+        let mut generics = input.generics.clone();
+        generics
+            .make_where_clause()
+            .predicates
+            .push(syn::parse_quote!(#key_ty: ::core::hash::Hash + ::core::cmp::Eq));
+
+        let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+        quote! {
+            #[automatically_derived]
+            impl #impl_generics ::extract_map::ExtractKey<#key_ty> for #name #ty_generics #where_clause {
+                fn extract_key(&self) -> &#key_ty {
+                    &self.#member
+                }
+            }
+        }
+    });
+
+    Ok(impls.collect())
+}
+
+/// Collects the fields marked with `#[extract_key]`, alongside how to access them.
+fn marked_fields(fields: &Fields) -> syn::Result<Vec<(Member, &Field)>> {
+    let mut key_fields = Vec::new();
+    for (i, field) in fields.iter().enumerate() {
+        let mut marked = false;
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|a| a.path().is_ident("extract_key"))
+        {
+            attr.meta.require_path_only()?;
+            if marked {
+                let msg = "duplicate `#[extract_key]` attribute";
+                return Err(Error::new_spanned(attr, msg));
+            }
+
+            marked = true;
+        }
+
+        if marked {
+            let member = match &field.ident {
+                Some(ident) => Member::Named(ident.clone()),
+                None => Member::Unnamed(Index {
+                    index: u32::try_from(i).expect("struct should have less than u32::MAX fields"),
+                    span: field.span(),
+                }),
+            };
+
+            key_fields.push((member, field));
+        }
+    }
+
+    Ok(key_fields)
+}
+
+/// Errors if multiple marked fields share a type, as `ExtractKey` would be implemented twice.
+fn check_distinct_key_types(key_fields: &[(Member, &Field)]) -> syn::Result<()> {
+    let mut errors: Option<Error> = None;
+    for (i, (_, field)) in key_fields.iter().enumerate() {
+        let key_ty = field.ty.to_token_stream().to_string();
+        let first = key_fields[..i]
+            .iter()
+            .find(|(_, other)| other.ty.to_token_stream().to_string() == key_ty);
+
+        if let Some((first_member, _)) = first {
+            let first_name = match first_member {
+                Member::Named(ident) => ident.to_string(),
+                Member::Unnamed(index) => index.index.to_string(),
+            };
+
+            let msg = format!(
+                "multiple fields are marked with `#[extract_key]` for the key type `{key_ty}`, \
+                first marked field is `{first_name}`"
+            );
+
+            let error = Error::new_spanned(&field.ty, msg);
+            match &mut errors {
+                Some(errors) => errors.combine(error),
+                None => errors = Some(error),
+            }
+        }
+    }
+
+    errors.map_or(Ok(()), Err)
+}
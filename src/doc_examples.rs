@@ -1,3 +1,6 @@
+#[cfg(feature = "serde")]
+use alloc::boxed::Box;
+
 use crate::ExtractKey;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -12,6 +15,20 @@ impl ExtractKey<u64> for User {
     }
 }
 
+/// A hasher which hashes every key to the same value, so every lookup has to compare keys.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Colliding;
+
+impl core::hash::Hasher for Colliding {
+    fn finish(&self) -> u64 {
+        0
+    }
+
+    fn write(&mut self, _bytes: &[u8]) {}
+}
+
+pub type CollidingState = core::hash::BuildHasherDefault<Colliding>;
+
 #[cfg(feature = "serde")]
 impl<'de> serde::Deserialize<'de> for User {
     fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
@@ -22,10 +39,27 @@ impl<'de> serde::Deserialize<'de> for User {
         impl<'a> serde::de::Visitor<'a> for Visitor {
             type Value = User;
 
-            fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            fn expecting(&self, formatter: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
                 formatter.write_str("a User struct")
             }
 
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::SeqAccess<'a>,
+            {
+                let id = seq
+                    .next_element()?
+                    .ok_or_else(|| Error::invalid_length(0, &self))?;
+                let name: Box<str> = seq
+                    .next_element()?
+                    .ok_or_else(|| Error::invalid_length(1, &self))?;
+
+                Ok(User {
+                    id,
+                    name: Box::leak(name),
+                })
+            }
+
             fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
             where
                 A: serde::de::MapAccess<'a>,
@@ -57,3 +91,15 @@ impl<'de> serde::Deserialize<'de> for User {
         deserializer.deserialize_struct("User", &["id", "name"], Visitor)
     }
 }
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for User {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("User", 2)?;
+        state.serialize_field("id", &self.id)?;
+        state.serialize_field("name", self.name)?;
+        state.end()
+    }
+}
@@ -1,64 +1,259 @@
 //! An implementation of the Entry API for [`ExtractMap`].
 
-use std::hash::{BuildHasher, Hash};
+use core::{
+    borrow::Borrow,
+    hash::{BuildHasher, Hash},
+    marker::PhantomData,
+};
 
-use crate::ExtractKey;
+use crate::{keys_match, ComputeKey, Equivalent, ExtractKey};
 
 use super::ExtractMap;
+use allocator_api2::alloc::{Allocator, Global};
 use hashbrown::hash_table::{
     Entry as RawEntry, OccupiedEntry as RawOccupiedEntry, VacantEntry as RawVacantEntry,
 };
 
 macro_rules! forward_debug {
-    ($type_name:ident) => {
-        impl<'a, V: std::fmt::Debug> std::fmt::Debug for $type_name<'a, V> {
-            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-                self.0.fmt(f)
+    (impl<$($param:ident),*> for $ty:ty) => {
+        impl<$($param: ?Sized,)* V: core::fmt::Debug, A: Allocator> core::fmt::Debug for $ty {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                self.raw.fmt(f)
             }
         }
     };
 }
 
-impl<K, V, S> ExtractMap<K, V, S>
+fn debug_assert_key_matches<K, V, Q>(key: &Q, value: &V)
 where
     K: Hash + Eq,
-    V: ExtractKey<K>,
+    V: ComputeKey<K>,
+    Q: Equivalent<K> + ?Sized,
+{
+    debug_assert!(
+        key.equivalent(value.compute_key().borrow()),
+        "the key of the value inserted does not match the key of the entry"
+    );
+}
+
+/// A simple FNV-1a hasher, used to check a key is unchanged in debug builds without cloning it.
+#[cfg(debug_assertions)]
+struct DebugKeyHasher(u64);
+
+#[cfg(debug_assertions)]
+impl Default for DebugKeyHasher {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+#[cfg(debug_assertions)]
+impl core::hash::Hasher for DebugKeyHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+#[cfg(debug_assertions)]
+fn debug_key_hash<K: Hash + Eq, V: ComputeKey<K>>(value: &V) -> u64 {
+    crate::hash_value(
+        &core::hash::BuildHasherDefault::<DebugKeyHasher>::default(),
+        value,
+    )
+}
+
+impl<K, V, S, A: Allocator> ExtractMap<K, V, S, A>
+where
+    K: Hash + Eq,
+    V: ComputeKey<K>,
     S: BuildHasher,
 {
     /// Gets the given key’s corresponding entry in the map for in-place manipulation.
-    pub fn entry(&mut self, key: &K) -> Entry<'_, V> {
-        Entry::from_raw(self.raw_entry(key))
+    ///
+    /// As with [`ExtractMap::get`], the key may be any borrowed form of the map's key type.
+    /// The key is kept in the [`Entry`], and can be retrieved with [`Entry::key`].
+    pub fn entry<'a, Q>(&'a mut self, key: &'a Q) -> Entry<'a, K, V, Q, A>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        match self.raw_entry(key) {
+            RawEntry::Occupied(raw) => Entry::Occupied(OccupiedEntry::new(raw)),
+            RawEntry::Vacant(raw) => Entry::Vacant(VacantEntry {
+                raw,
+                key,
+                phantom: PhantomData,
+            }),
+        }
+    }
+
+    /// Tries to insert a value into the [`ExtractMap`], without replacing an existing value with the same key.
+    ///
+    /// Returns a mutable reference to the inserted value, which must not have its key mutated.
+    ///
+    /// # Errors
+    /// If a value with the same key already exists, an [`OccupiedError`] is returned containing the
+    /// [`OccupiedEntry`] of the existing value and the value which was not inserted.
+    ///
+    /// # Examples
+    /// ```
+    /// use extract_map::ExtractMap;
+    /// # use extract_map::doc_examples::User;
+    ///
+    /// let mut map = ExtractMap::new();
+    /// assert!(map.try_insert(User { id: 1, name: "Daisy" }).is_ok());
+    ///
+    /// let err = map.try_insert(User { id: 1, name: "Elliott" }).unwrap_err();
+    /// assert_eq!(err.entry.get(), &User { id: 1, name: "Daisy" });
+    /// assert_eq!(err.value, User { id: 1, name: "Elliott" });
+    /// ```
+    pub fn try_insert(&mut self, value: V) -> Result<&mut V, OccupiedError<'_, K, V, A>> {
+        match self.raw_entry_for(&value) {
+            RawEntry::Occupied(entry) => Err(OccupiedError {
+                entry: OccupiedEntry::new(entry),
+                value,
+            }),
+            RawEntry::Vacant(entry) => Ok(entry.insert(value).into_mut()),
+        }
+    }
+}
+
+/// The error returned from [`ExtractMap::try_insert`] when a value with the same key already exists.
+pub struct OccupiedError<'a, K: ?Sized, V, A: Allocator = Global> {
+    /// The entry of the value already in the map.
+    pub entry: OccupiedEntry<'a, K, V, A>,
+    /// The value which was not inserted.
+    pub value: V,
+}
+
+impl<K: ?Sized, V: core::fmt::Debug, A: Allocator> core::fmt::Debug for OccupiedError<'_, K, V, A> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("OccupiedError")
+            .field("old_value", self.entry.get())
+            .field("new_value", &self.value)
+            .finish()
+    }
+}
+
+impl<K: ?Sized, V: core::fmt::Debug, A: Allocator> core::fmt::Display
+    for OccupiedError<'_, K, V, A>
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "failed to insert {:?}, a value with the same key already exists: {:?}",
+            self.value,
+            self.entry.get(),
+        )
     }
 }
 
+#[cfg(feature = "std")]
+impl<K: ?Sized, V: core::fmt::Debug, A: Allocator> std::error::Error
+    for OccupiedError<'_, K, V, A>
+{
+}
+
+/// The error returned from [`Entry::insert_checked`] and [`VacantEntry::insert_checked`] when the key of the value
+/// does not match the key of the entry.
+pub struct KeyMismatchError<V> {
+    /// The value which was not inserted.
+    pub value: V,
+}
+
+impl<V: core::fmt::Debug> core::fmt::Debug for KeyMismatchError<V> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("KeyMismatchError")
+            .field("value", &self.value)
+            .finish()
+    }
+}
+
+impl<V: core::fmt::Debug> core::fmt::Display for KeyMismatchError<V> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "failed to insert {:?}, the key of the value does not match the key of the entry",
+            self.value,
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl<V: core::fmt::Debug> std::error::Error for KeyMismatchError<V> {}
+
 /// A view into a single entry in a table, which may either be vacant or occupied.
 ///
-/// This enum is constructed from [`ExtractMap::entry`].
+/// This enum is constructed from [`ExtractMap::entry`], and `Q` is the type of the key used to look it up.
 #[derive(Debug)]
-pub enum Entry<'a, V> {
+pub enum Entry<'a, K: ?Sized, V, Q: ?Sized = K, A: Allocator = Global> {
     /// An occupied entry.
-    Occupied(OccupiedEntry<'a, V>),
+    Occupied(OccupiedEntry<'a, K, V, A>),
     /// A vacant entry.
-    Vacant(VacantEntry<'a, V>),
+    Vacant(VacantEntry<'a, K, V, Q, A>),
 }
 
-impl<'a, V> Entry<'a, V> {
-    fn from_raw(raw: RawEntry<'a, V>) -> Self {
-        match raw {
-            RawEntry::Occupied(raw_entry) => Entry::Occupied(OccupiedEntry(raw_entry)),
-            RawEntry::Vacant(raw_entry) => Entry::Vacant(VacantEntry(raw_entry)),
+impl<K, V, Q, A> Entry<'_, K, V, Q, A>
+where
+    K: Hash + Eq + Borrow<Q>,
+    V: ExtractKey<K>,
+    Q: ?Sized,
+    A: Allocator,
+{
+    /// Gets a reference to the key of the entry.
+    ///
+    /// For an occupied entry this is the key of the value in the map, and for a vacant entry this is
+    /// the key passed to [`ExtractMap::entry`].
+    ///
+    /// This requires the values to implement [`ExtractKey`], as a key computed by [`ComputeKey`] cannot be
+    /// borrowed from the value. Otherwise, use [`OccupiedEntry::key`] or [`VacantEntry::key`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use extract_map::ExtractMap;
+    /// # use extract_map::doc_examples::User;
+    ///
+    /// let mut map: ExtractMap<u64, User> = ExtractMap::new();
+    /// map.insert(User { id: 1, name: "Cat" });
+    ///
+    /// assert_eq!(map.entry(&1).key(), &1);
+    /// assert_eq!(map.entry(&2).key(), &2);
+    /// ```
+    #[must_use]
+    pub fn key(&self) -> &Q {
+        match self {
+            Entry::Occupied(entry) => entry.raw.get().extract_key().borrow(),
+            Entry::Vacant(entry) => entry.key(),
         }
     }
+}
 
-    fn into_raw(self) -> RawEntry<'a, V> {
+impl<'a, K, V, Q, A> Entry<'a, K, V, Q, A>
+where
+    K: Hash + Eq,
+    V: ComputeKey<K>,
+    Q: Equivalent<K> + ?Sized,
+    A: Allocator,
+{
+    fn key_matches(&self, value: &V) -> bool {
         match self {
-            Entry::Occupied(entry) => RawEntry::Occupied(entry.0),
-            Entry::Vacant(entry) => RawEntry::Vacant(entry.0),
+            Entry::Occupied(entry) => keys_match(entry.get(), value),
+            Entry::Vacant(entry) => entry.key.equivalent(value.compute_key().borrow()),
         }
     }
 
     /// Sets the value of the entry, replacing any existing value if there is one, and returns an [`OccupiedEntry`].
     ///
+    /// # Panics
+    /// In debug builds, panics if the key of the value does not match the key of the entry.
+    /// Use [`Entry::insert_checked`] to check this in release builds.
+    ///
     /// # Example
     ///
     /// ```
@@ -71,8 +266,48 @@ impl<'a, V> Entry<'a, V> {
     /// let entry = map.entry(&1).insert(User { id: 1, name: "Fox" });
     /// assert_eq!(entry.get(), &User { id: 1, name: "Fox" });
     /// ```
-    pub fn insert(self, value: V) -> OccupiedEntry<'a, V> {
-        OccupiedEntry(self.into_raw().insert(value))
+    pub fn insert(self, value: V) -> OccupiedEntry<'a, K, V, A> {
+        match self {
+            Entry::Occupied(mut entry) => {
+                entry.insert(value);
+                entry
+            }
+            Entry::Vacant(entry) => entry.insert(value),
+        }
+    }
+
+    /// Sets the value of the entry, replacing any existing value if there is one, and returns an [`OccupiedEntry`].
+    ///
+    /// Unlike [`ExtractMap::try_insert`], which errors if a value with the same key already exists, this only
+    /// checks that the value belongs in this entry.
+    ///
+    /// # Errors
+    /// Errors if the key of the value does not match the key of the entry, returning the value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use extract_map::ExtractMap;
+    /// # use extract_map::doc_examples::User;
+    ///
+    /// let mut map: ExtractMap<u64, User> = ExtractMap::new();
+    ///
+    /// let err = map.entry(&1).insert_checked(User { id: 2, name: "Fox" }).unwrap_err();
+    /// assert_eq!(err.value, User { id: 2, name: "Fox" });
+    /// assert!(map.is_empty());
+    ///
+    /// let entry = map.entry(&1).insert_checked(User { id: 1, name: "Fox" }).unwrap();
+    /// assert_eq!(entry.get(), &User { id: 1, name: "Fox" });
+    /// ```
+    pub fn insert_checked(
+        self,
+        value: V,
+    ) -> Result<OccupiedEntry<'a, K, V, A>, KeyMismatchError<V>> {
+        if self.key_matches(&value) {
+            Ok(self.insert(value))
+        } else {
+            Err(KeyMismatchError { value })
+        }
     }
 
     /// Ensures a value is in the entry by inserting if it was vacant.
@@ -95,8 +330,8 @@ impl<'a, V> Entry<'a, V> {
     /// let entry = map.entry(&1).or_insert(User { id: 1, name: "Cat" });
     /// assert_eq!(entry.get(), &User { id: 1, name: "Fox" });
     /// ```
-    pub fn or_insert(self, default: V) -> OccupiedEntry<'a, V> {
-        OccupiedEntry(self.into_raw().or_insert(default))
+    pub fn or_insert(self, default: V) -> OccupiedEntry<'a, K, V, A> {
+        self.or_insert_with(|| default)
     }
 
     /// Ensures a value is in the entry by inserting the result of the function if it was vacant.
@@ -119,10 +354,36 @@ impl<'a, V> Entry<'a, V> {
     /// let entry = map.entry(&1).or_insert_with(|| User { id: 1, name: "Cat" });
     /// assert_eq!(entry.get(), &User { id: 1, name: "Fox" });
     /// ```
-    pub fn or_insert_with(self, default: impl FnOnce() -> V) -> OccupiedEntry<'a, V> {
-        OccupiedEntry(self.into_raw().or_insert_with(default))
+    pub fn or_insert_with(self, default: impl FnOnce() -> V) -> OccupiedEntry<'a, K, V, A> {
+        self.or_insert_with_key(|_| default())
+    }
+
+    /// Ensures a value is in the entry by inserting the result of the function if it was vacant.
+    ///
+    /// The function is passed the key used to look up the entry, allowing the value to be constructed from it.
+    ///
+    /// Returns an [`OccupiedEntry`] pointing to the now-occupied entry.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use extract_map::ExtractMap;
+    /// # use extract_map::doc_examples::User;
+    ///
+    /// let mut map: ExtractMap<u64, User> = ExtractMap::new();
+    ///
+    /// let entry = map.entry(&1).or_insert_with_key(|&id| User { id, name: "Fox" });
+    /// assert_eq!(entry.get(), &User { id: 1, name: "Fox" });
+    /// ```
+    pub fn or_insert_with_key(self, default: impl FnOnce(&Q) -> V) -> OccupiedEntry<'a, K, V, A> {
+        match self {
+            Entry::Occupied(entry) => entry,
+            Entry::Vacant(entry) => entry.insert_with_key(default),
+        }
     }
+}
 
+impl<K: ?Sized, V, Q: ?Sized, A: Allocator> Entry<'_, K, V, Q, A> {
     /// Provides in-place mutable access to an occupied entry, does nothing for a vacant entry.
     ///
     /// # Example
@@ -140,16 +401,120 @@ impl<'a, V> Entry<'a, V> {
     /// ```
     #[allow(clippy::return_self_not_must_use)]
     pub fn and_modify(self, f: impl FnOnce(&mut V)) -> Self {
-        Self::from_raw(self.into_raw().and_modify(f))
+        match self {
+            Entry::Occupied(mut entry) => {
+                f(entry.get_mut());
+                Entry::Occupied(entry)
+            }
+            Entry::Vacant(entry) => Entry::Vacant(entry),
+        }
     }
 }
 
 /// A view into an occupied entry in an [`ExtractMap`]. It is part of the [`Entry`] enum.
-pub struct OccupiedEntry<'a, V>(RawOccupiedEntry<'a, V>);
+pub struct OccupiedEntry<'a, K: ?Sized, V, A: Allocator = Global> {
+    raw: RawOccupiedEntry<'a, V, A>,
+    phantom: PhantomData<K>,
+}
+
+forward_debug!(impl<K> for OccupiedEntry<'_, K, V, A>);
+
+impl<K, V, A> OccupiedEntry<'_, K, V, A>
+where
+    K: Hash + Eq,
+    V: ComputeKey<K>,
+    A: Allocator,
+{
+    /// Gets a reference to the key of the value in the entry.
+    #[must_use]
+    pub fn key(&self) -> V::Key<'_> {
+        self.raw.get().compute_key()
+    }
 
-forward_debug!(OccupiedEntry);
+    /// Sets the value of the entry, and returns the entry’s old value.
+    ///
+    /// This is equivalent to [`std::mem::replace`] with [`Self::get_mut`].
+    ///
+    /// # Panics
+    /// In debug builds, panics if the key of the value does not match the key of the entry.
+    pub fn insert(&mut self, value: V) -> V {
+        #[cfg(debug_assertions)]
+        debug_assert_key_matches::<K, V, K>(self.key().borrow(), &value);
+
+        core::mem::replace(self.raw.get_mut(), value)
+    }
+
+    /// Replaces the value of the entry with the result of the function, or removes it if [`None`] is returned.
+    ///
+    /// The function is passed the current value by ownership, and the value it returns must have the same key.
+    /// In debug builds, the key is hashed before calling the function, so a changed key can be detected.
+    ///
+    /// Returns the [`OccupiedEntry`] if the value was replaced, or [`None`] if it was removed.
+    ///
+    /// # Panics
+    /// In debug builds, panics if the key of the returned value does not match the key of the entry.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use extract_map::{ExtractMap, entry::Entry};
+    /// # use extract_map::doc_examples::User;
+    ///
+    /// let mut map: ExtractMap<u64, User> = ExtractMap::new();
+    /// map.insert(User { id: 1, name: "Cat" });
+    /// map.insert(User { id: 2, name: "Dog" });
+    ///
+    /// if let Entry::Occupied(entry) = map.entry(&1) {
+    ///     let entry = entry.replace_entry_with(|user| Some(User { name: "Fox", ..user }));
+    ///     assert_eq!(entry.unwrap().get(), &User { id: 1, name: "Fox" });
+    /// }
+    ///
+    /// if let Entry::Occupied(entry) = map.entry(&2) {
+    ///     assert!(entry.replace_entry_with(|_| None).is_none());
+    /// }
+    ///
+    /// assert_eq!(map.len(), 1);
+    /// ```
+    ///
+    /// Returning a value with a different key panics in debug builds.
+    /// ```should_panic
+    /// use extract_map::{ExtractMap, entry::Entry};
+    /// # use extract_map::doc_examples::User;
+    ///
+    /// let mut map: ExtractMap<u64, User> = ExtractMap::new();
+    /// map.insert(User { id: 1, name: "Cat" });
+    ///
+    /// if let Entry::Occupied(entry) = map.entry(&1) {
+    ///     let _ = entry.replace_entry_with(|user| Some(User { id: 5, ..user }));
+    /// }
+    /// ```
+    #[must_use = "The returned entry can be dropped if not needed"]
+    pub fn replace_entry_with(self, f: impl FnOnce(V) -> Option<V>) -> Option<Self> {
+        #[cfg(debug_assertions)]
+        let key_hash = debug_key_hash(self.raw.get());
+
+        let (value, vacant) = self.raw.remove();
+        f(value).map(|value| {
+            #[cfg(debug_assertions)]
+            debug_assert_eq!(
+                key_hash,
+                debug_key_hash(&value),
+                "the key of the value inserted does not match the key of the entry"
+            );
+
+            Self::new(vacant.insert(value))
+        })
+    }
+}
+
+impl<'a, K: ?Sized, V, A: Allocator> OccupiedEntry<'a, K, V, A> {
+    fn new(raw: RawOccupiedEntry<'a, V, A>) -> Self {
+        Self {
+            raw,
+            phantom: PhantomData,
+        }
+    }
 
-impl<'a, V> OccupiedEntry<'a, V> {
     /// Removes the value from the map.
     ///
     /// # Example
@@ -168,7 +533,7 @@ impl<'a, V> OccupiedEntry<'a, V> {
     /// ```
     #[allow(clippy::must_use_candidate)]
     pub fn remove(self) -> V {
-        self.0.remove().0
+        self.raw.remove().0
     }
 
     /// Gets a reference to the value from the map.
@@ -188,7 +553,7 @@ impl<'a, V> OccupiedEntry<'a, V> {
     /// ```
     #[must_use]
     pub fn get(&self) -> &V {
-        self.0.get()
+        self.raw.get()
     }
 
     /// Gets a mutable reference to the value from the map.
@@ -211,7 +576,7 @@ impl<'a, V> OccupiedEntry<'a, V> {
     /// assert_eq!(map.get(&1), Some(&User { id: 1, name: "Fox" }));
     /// ```
     pub fn get_mut(&mut self) -> &mut V {
-        self.0.get_mut()
+        self.raw.get_mut()
     }
 
     /// Converts the [`OccupiedEntry`] into a mutable reference to the value from the map.
@@ -238,25 +603,79 @@ impl<'a, V> OccupiedEntry<'a, V> {
     /// ```
     #[must_use]
     pub fn into_mut(self) -> &'a mut V {
-        self.0.into_mut()
+        self.raw.into_mut()
     }
+}
 
-    /// Sets the value of the entry, and returns the entry’s old value.
+/// A view into a vacant entry in an [`ExtractMap`]. It is part of the [`Entry`] enum.
+pub struct VacantEntry<'a, K: ?Sized, V, Q: ?Sized = K, A: Allocator = Global> {
+    raw: RawVacantEntry<'a, V, A>,
+    key: &'a Q,
+    phantom: PhantomData<K>,
+}
+
+forward_debug!(impl<K, Q> for VacantEntry<'_, K, V, Q, A>);
+
+impl<'a, K: ?Sized, V, Q: ?Sized, A: Allocator> VacantEntry<'a, K, V, Q, A> {
+    /// Gets a reference to the key passed to [`ExtractMap::entry`].
     ///
-    /// This is equivalent to [`std::mem::replace`] with [`Self::get_mut`].
-    pub fn insert(&mut self, value: V) -> V {
-        std::mem::replace(self.0.get_mut(), value)
+    /// # Example
+    ///
+    /// ```
+    /// use extract_map::{ExtractMap, entry::Entry};
+    /// # use extract_map::doc_examples::User;
+    ///
+    /// let mut map: ExtractMap<u64, User> = ExtractMap::new();
+    ///
+    /// if let Entry::Vacant(entry) = map.entry(&1) {
+    ///     assert_eq!(entry.key(), &1);
+    /// }
+    /// ```
+    #[must_use]
+    pub fn key(&self) -> &'a Q {
+        self.key
     }
 }
 
-/// A view into a vacant entry in an [`ExtractMap`]. It is part of the [`Entry`] enum.
-pub struct VacantEntry<'a, V>(RawVacantEntry<'a, V>);
-
-forward_debug!(VacantEntry);
+impl<'a, K, V, Q, A> VacantEntry<'a, K, V, Q, A>
+where
+    K: Hash + Eq,
+    V: ComputeKey<K>,
+    Q: Equivalent<K> + ?Sized,
+    A: Allocator,
+{
+    /// Sets the value of the entry with the [`VacantEntry`]’s key, and returns an [`OccupiedEntry`].
+    ///
+    /// # Panics
+    /// In debug builds, panics if the key of the value does not match the key of the entry.
+    /// Use [`VacantEntry::insert_checked`] to check this in release builds.
+    pub fn insert(self, value: V) -> OccupiedEntry<'a, K, V, A> {
+        debug_assert_key_matches(self.key, &value);
+        OccupiedEntry::new(self.raw.insert(value))
+    }
 
-impl<'a, V> VacantEntry<'a, V> {
     /// Sets the value of the entry with the [`VacantEntry`]’s key, and returns an [`OccupiedEntry`].
-    pub fn insert(self, value: V) -> OccupiedEntry<'a, V> {
-        OccupiedEntry(self.0.insert(value))
+    ///
+    /// This is the same as [`Entry::insert_checked`], checking that the value belongs in this entry.
+    ///
+    /// # Errors
+    /// Errors if the key of the value does not match the key of the entry, returning the value.
+    pub fn insert_checked(
+        self,
+        value: V,
+    ) -> Result<OccupiedEntry<'a, K, V, A>, KeyMismatchError<V>> {
+        if self.key.equivalent(value.compute_key().borrow()) {
+            Ok(OccupiedEntry::new(self.raw.insert(value)))
+        } else {
+            Err(KeyMismatchError { value })
+        }
+    }
+
+    /// Sets the value of the entry to the result of the function, and returns an [`OccupiedEntry`].
+    ///
+    /// The function is passed the [`VacantEntry`]’s key, allowing the value to be constructed from it.
+    pub fn insert_with_key(self, f: impl FnOnce(&Q) -> V) -> OccupiedEntry<'a, K, V, A> {
+        let value = f(self.key);
+        self.insert(value)
     }
 }
@@ -0,0 +1,131 @@
+//! Integrity checking for [`ExtractMap`], to detect values stored under a stale key.
+
+use alloc::vec::Vec;
+use core::hash::{BuildHasher, Hash};
+
+use allocator_api2::alloc::Allocator;
+use hashbrown::{hash_table::Entry as RawEntry, HashTable};
+
+use crate::{hash_value, keys_match, ComputeKey, ExtractMap};
+
+/// A report of the problems found by [`ExtractMap::check_integrity`].
+///
+/// `K` is the [`ComputeKey::Key`] of the values, which is a reference to the key for values implementing
+/// [`ExtractKey`](crate::ExtractKey).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntegrityReport<K> {
+    /// The keys of values which cannot be found by looking up their own key.
+    ///
+    /// This happens if the key of a value is mutated without the map being informed.
+    pub misplaced: Vec<K>,
+    /// The keys which are shared by more than one value, with one entry per extra value.
+    pub duplicates: Vec<K>,
+}
+
+impl<K> IntegrityReport<K> {
+    /// Returns `true` if no problems were found.
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        self.misplaced.is_empty() && self.duplicates.is_empty()
+    }
+}
+
+impl<K, V, S, A: Allocator> ExtractMap<K, V, S, A>
+where
+    K: Hash + Eq,
+    V: ComputeKey<K>,
+    S: BuildHasher,
+{
+    /// Checks that every value is stored under the hash of its current key, and that no keys are duplicated.
+    ///
+    /// This should never find problems unless the key of a value has been mutated through a method which
+    /// does not allow it, such as [`ExtractMap::iter_mut`]. If problems are found, they can be fixed with
+    /// [`ExtractMap::rehash_all`].
+    ///
+    /// # Examples
+    /// ```
+    /// use extract_map::ExtractMap;
+    /// # use extract_map::doc_examples::User;
+    ///
+    /// let mut map = ExtractMap::new();
+    /// map.insert(User { id: 1, name: "Daisy" });
+    /// map.insert(User { id: 2, name: "Elliott" });
+    /// assert!(map.check_integrity().is_valid());
+    ///
+    /// // Mutating the key through `iter_mut` is not allowed, and leaves the value misplaced.
+    /// map.iter_mut().find(|user| user.id == 2).unwrap().id = 1;
+    ///
+    /// let report = map.check_integrity();
+    /// assert_eq!(report.misplaced, [&1]);
+    /// assert_eq!(report.duplicates, [&1]);
+    ///
+    /// map.rehash_all();
+    /// assert!(map.check_integrity().is_valid());
+    /// assert_eq!(map.len(), 1);
+    /// ```
+    ///
+    /// For values implementing [`ComputeKey`], the report contains the computed keys.
+    /// ```
+    /// use extract_map::{ComputeKey, ExtractMap};
+    ///
+    /// struct Membership {
+    ///     tenant_id: u32,
+    ///     user_id: u32,
+    /// }
+    ///
+    /// impl ComputeKey<(u32, u32)> for Membership {
+    ///     type Key<'a> = (u32, u32);
+    ///
+    ///     fn compute_key(&self) -> (u32, u32) {
+    ///         (self.tenant_id, self.user_id)
+    ///     }
+    /// }
+    ///
+    /// let mut memberships = ExtractMap::<(u32, u32), Membership>::new();
+    /// memberships.insert(Membership { tenant_id: 1, user_id: 5 });
+    /// memberships.insert(Membership { tenant_id: 2, user_id: 5 });
+    ///
+    /// memberships.iter_mut().for_each(|membership| membership.tenant_id = 3);
+    /// assert_eq!(memberships.check_integrity().duplicates, [(3, 5)]);
+    /// ```
+    #[must_use]
+    pub fn check_integrity(&self) -> IntegrityReport<V::Key<'_>> {
+        let mut misplaced = Vec::new();
+        let mut duplicates = Vec::new();
+        let mut seen = HashTable::with_capacity(self.len());
+        let build_hasher = &self.build_hasher;
+
+        for value in &self.table {
+            let hash = hash_value(build_hasher, value);
+
+            if !self.table.iter_hash(hash).any(|v| core::ptr::eq(v, value)) {
+                misplaced.push(value.compute_key());
+            }
+
+            match seen.entry(
+                hash,
+                |&v| keys_match(v, value),
+                |&v| hash_value(build_hasher, v),
+            ) {
+                RawEntry::Occupied(_) => duplicates.push(value.compute_key()),
+                RawEntry::Vacant(entry) => {
+                    entry.insert(value);
+                }
+            }
+        }
+
+        IntegrityReport {
+            misplaced,
+            duplicates,
+        }
+    }
+
+    /// Rebuilds the map, storing every value under the hash of its current key.
+    ///
+    /// This fixes any problems found by [`ExtractMap::check_integrity`]. If multiple values have the same key,
+    /// only one is kept, and which one is unspecified.
+    pub fn rehash_all(&mut self) {
+        let values: Vec<V> = self.table.drain().collect();
+        self.extend(values);
+    }
+}
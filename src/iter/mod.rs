@@ -1,22 +1,39 @@
 #![allow(clippy::module_name_repetitions)]
 
+use allocator_api2::alloc::{Allocator, Global};
+
 use super::ExtractMap;
 macro_rules! forward_iterator {
     (
-        pub struct $ty_name:ident<$($lt:lifetime,)? V>($inner_ty:ty),
+        pub struct $ty_name:ident<$($lt:lifetime,)? V $(, $alloc:ident)?>($inner_ty:ty),
         $item:ty,
         |$var:ident: $map:ty| $inner:expr
+    ) => {
+        forward_iterator!(pub struct $ty_name<$($lt,)* V $(, $alloc)?>($inner_ty), $item);
+
+        impl<$($lt,)* K, V, S, A: Allocator> IntoIterator for $map {
+            type Item = $item;
+            type IntoIter = $ty_name<$($lt,)* V $(, $alloc)?>;
+
+            fn into_iter(self) -> Self::IntoIter {
+                $ty_name((|$var: $map|$inner)(self))
+            }
+        }
+    };
+    (
+        pub struct $ty_name:ident<$($lt:lifetime,)? V $(, $alloc:ident)?>($inner_ty:ty),
+        $item:ty
     ) => {
         #[must_use = "Iterators do nothing if not consumed"]
-        pub struct $ty_name<$($lt,)* V>($inner_ty);
+        pub struct $ty_name<$($lt,)* V $(, $alloc: Allocator = Global)?>(pub(crate) $inner_ty);
 
-        impl<$($lt,)* V: std::fmt::Debug> std::fmt::Debug for $ty_name<$($lt,)* V> {
-            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        impl<$($lt,)* V: core::fmt::Debug $(, $alloc: Allocator)?> core::fmt::Debug for $ty_name<$($lt,)* V $(, $alloc)?> {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
                 self.0.fmt(f)
             }
         }
 
-        impl<$($lt,)* V> Iterator for $ty_name<$($lt,)* V> {
+        impl<$($lt,)* V $(, $alloc: Allocator)?> Iterator for $ty_name<$($lt,)* V $(, $alloc)?> {
             type Item = $item;
 
             fn next(&mut self) -> Option<Self::Item> {
@@ -28,35 +45,26 @@ macro_rules! forward_iterator {
             }
         }
 
-        impl<$($lt,)* V> ExactSizeIterator for $ty_name<$($lt,)* V> {
+        impl<$($lt,)* V $(, $alloc: Allocator)?> ExactSizeIterator for $ty_name<$($lt,)* V $(, $alloc)?> {
             fn len(&self) -> usize {
                 self.0.len()
             }
         }
 
-        impl<$($lt,)* V> std::iter::FusedIterator for $ty_name<$($lt,)* V> {}
-
-        impl<$($lt,)* K, V, S> IntoIterator for $map {
-            type Item = $item;
-            type IntoIter = $ty_name<$($lt,)* V>;
-
-            fn into_iter(self) -> Self::IntoIter {
-                $ty_name((|$var: $map|$inner)(self))
-            }
-        }
+        impl<$($lt,)* V $(, $alloc: Allocator)?> core::iter::FusedIterator for $ty_name<$($lt,)* V $(, $alloc)?> {}
     };
 }
 
 forward_iterator!(
-    pub struct IntoIter<V>(hashbrown::hash_table::IntoIter<V>),
+    pub struct IntoIter<V, A>(hashbrown::hash_table::IntoIter<V, A>),
     V,
-    |map: ExtractMap<K, V, S>| map.table.into_iter()
+    |map: ExtractMap<K, V, S, A>| map.table.into_iter()
 );
 
 forward_iterator!(
     pub struct Iter<'a, V>(hashbrown::hash_table::Iter<'a, V>),
     &'a V,
-    |map: &'a ExtractMap<K, V, S>| map.table.iter()
+    |map: &'a ExtractMap<K, V, S, A>| map.table.iter()
 );
 
 impl<V> Clone for Iter<'_, V> {
@@ -72,5 +80,47 @@ impl<V> Clone for Iter<'_, V> {
 forward_iterator!(
     pub struct IterMut<'a, V>(hashbrown::hash_table::IterMut<'a, V>),
     &'a mut V,
-    |map: &'a mut ExtractMap<K, V, S>| map.table.iter_mut()
+    |map: &'a mut ExtractMap<K, V, S, A>| map.table.iter_mut()
+);
+
+forward_iterator!(
+    pub struct Drain<'a, V, A>(hashbrown::hash_table::Drain<'a, V, A>),
+    V
 );
+
+/// An iterator which removes and yields values matching a predicate, created by [`ExtractMap::extract_if`].
+#[must_use = "Iterators are lazy and will not remove any values if not consumed"]
+pub struct ExtractIf<'a, V, F, A: Allocator = Global>(
+    pub(crate) hashbrown::hash_table::ExtractIf<'a, V, F, A>,
+)
+where
+    F: FnMut(&mut V) -> bool;
+
+impl<V, F, A: Allocator> Iterator for ExtractIf<'_, V, F, A>
+where
+    F: FnMut(&mut V) -> bool,
+{
+    type Item = V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<V, F, A: Allocator> core::iter::FusedIterator for ExtractIf<'_, V, F, A> where
+    F: FnMut(&mut V) -> bool
+{
+}
+
+impl<V, F, A: Allocator> core::fmt::Debug for ExtractIf<'_, V, F, A>
+where
+    F: FnMut(&mut V) -> bool,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ExtractIf").finish_non_exhaustive()
+    }
+}
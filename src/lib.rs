@@ -3,34 +3,105 @@
 //! ## MSRV
 //!
 //! The Minimum Supported Rust Version for this crate is 1.70, and raising it is considered a breaking change.
+//!
+//! ## `no_std`
+//!
+//! This crate supports `no_std` environments with `alloc` by disabling the default `std` feature.
+//! Without `std`, [`ExtractMap`] has no default hasher, so one must be provided via [`ExtractMap::with_hasher`]
+//! and the [`MutGuard`] unwind policy has no effect.
+#![no_std]
 #![warn(clippy::pedantic, rust_2018_idioms, missing_docs)]
 
-use std::{
-    collections::hash_map::RandomState,
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+#[cfg(target_has_atomic = "ptr")]
+use alloc::sync::Arc;
+use alloc::{boxed::Box, rc::Rc, vec::Vec};
+use allocator_api2::alloc::{Allocator, Global};
+use core::{
+    borrow::Borrow,
     fmt::Debug,
     hash::{BuildHasher, Hash, Hasher as _},
     marker::PhantomData,
-    mem::{replace, ManuallyDrop},
+    mem::replace,
 };
 
 use hashbrown::{hash_table::Entry as RawEntry, HashTable};
 use mut_guard::MutGuard;
+#[cfg(feature = "std")]
+use std::collections::hash_map::RandomState;
+
+/// The `allocator-api2` crate, re-exported to name [`Allocator`] and [`Global`] without a separate dependency.
+pub use allocator_api2;
+pub use hashbrown::{Equivalent, TryReserveError};
 
 #[doc(hidden)]
 pub mod doc_examples;
 pub mod entry;
+pub mod integrity;
 #[doc(hidden)]
 pub mod iter;
-mod mut_guard;
+pub mod mut_guard;
+#[cfg(feature = "rayon")]
+pub mod rayon;
+pub mod rekey;
 #[cfg(feature = "serde")]
 mod serde;
 #[cfg(feature = "typesize")]
 mod typesize;
 
+#[cfg(feature = "derive")]
+pub use extract_map_derive::ExtractKey;
 #[cfg(feature = "serde")]
-pub use serde::serialize_as_map;
+pub use serde::{
+    serde_as_map, serde_as_seq, serde_as_strict_map, serialize_as_map, serialize_as_sorted_map,
+    serialize_sorted, DuplicatePolicy, ElementError, ExtractMapSeed, FromMap, FromSeq, LenientMap,
+    LenientSeed,
+};
+
+fn all_some<T, const N: usize>(array: [Option<T>; N]) -> Option<[T; N]> {
+    if array.iter().all(Option::is_some) {
+        Some(array.map(Option::unwrap))
+    } else {
+        None
+    }
+}
+
+fn hash_value<K, V, S>(build_hasher: &S, value: &V) -> u64
+where
+    K: Hash + Eq,
+    V: ComputeKey<K> + ?Sized,
+    S: BuildHasher,
+{
+    let key = value.compute_key();
+    hash_one::<S, K>(build_hasher, key.borrow())
+}
+
+fn keys_match<K: Hash + Eq, V: ComputeKey<K> + ?Sized>(a: &V, b: &V) -> bool {
+    let (a, b) = (a.compute_key(), b.compute_key());
+    Borrow::<K>::borrow(&a) == Borrow::<K>::borrow(&b)
+}
+
+/// Checks if another value with the same hash as `value`, which must be stored in `table`, has the same key.
+///
+/// `value` is only compared by address, and is never dereferenced.
+fn has_duplicate_key<K, V, A>(table: &HashTable<V, A>, hash: u64, value: *const V) -> bool
+where
+    K: Hash + Eq,
+    V: ComputeKey<K>,
+    A: Allocator,
+{
+    let mut colliding = table.iter_hash(hash);
+    let Some(value) = colliding.clone().find(|v| core::ptr::eq(*v, value)) else {
+        return false;
+    };
+
+    colliding.any(|v| !core::ptr::eq(v, value) && keys_match(v, value))
+}
 
-fn hash_one<S: BuildHasher, H: Hash>(build_hasher: &S, val: H) -> u64 {
+fn hash_one<S: BuildHasher, H: Hash + ?Sized>(build_hasher: &S, val: &H) -> u64 {
     let mut hasher = build_hasher.build_hasher();
     val.hash(&mut hasher);
     hasher.finish()
@@ -41,11 +112,117 @@ fn hash_one<S: BuildHasher, H: Hash>(build_hasher: &S, val: H) -> u64 {
 /// This is relied on for correctness in the same way as [`Hash`] and [`Eq`] are and
 /// is purely designed for directly referencing a field with no interior mutability or
 /// static return type.
+///
+/// This is implemented for references and smart pointers to values implementing [`ExtractKey`],
+/// allowing the same value to be stored in multiple maps, and for `(K, T)` tuples, allowing
+/// [`ExtractMap`] to be used as a plain key-value map.
+///
+/// # Examples
+/// ```
+/// use std::sync::Arc;
+///
+/// use extract_map::ExtractMap;
+/// # use extract_map::doc_examples::User;
+///
+/// let user = Arc::new(User { id: 1, name: "Elliott" });
+///
+/// let mut users: ExtractMap<u64, Arc<User>> = ExtractMap::new();
+/// users.insert(Arc::clone(&user));
+///
+/// assert!(Arc::ptr_eq(users.get(&1).unwrap(), &user));
+/// ```
+///
+/// ```
+/// use extract_map::ExtractMap;
+///
+/// let mut names: ExtractMap<u64, (u64, &str)> = ExtractMap::new();
+/// names.insert((1, "Elliott"));
+/// names.insert((2, "Daisy"));
+///
+/// assert_eq!(names.get(&2), Some(&(2, "Daisy")));
+/// ```
 pub trait ExtractKey<K: Hash + Eq> {
     /// Extracts the key that this value should be referred to with.
     fn extract_key(&self) -> &K;
 }
 
+macro_rules! forward_extract_key {
+    ($(#[$meta:meta])* impl for $ty:ty) => {
+        $(#[$meta])*
+        impl<K: Hash + Eq, V: ExtractKey<K> + ?Sized> ExtractKey<K> for $ty {
+            fn extract_key(&self) -> &K {
+                V::extract_key(self)
+            }
+        }
+    };
+}
+
+forward_extract_key!(impl for &V);
+forward_extract_key!(impl for Box<V>);
+forward_extract_key!(impl for Rc<V>);
+forward_extract_key!(#[cfg(target_has_atomic = "ptr")] impl for Arc<V>);
+
+impl<K: Hash + Eq, T> ExtractKey<K> for (K, T) {
+    fn extract_key(&self) -> &K {
+        &self.0
+    }
+}
+
+/// A trait for computing the key for an [`ExtractMap`], for values which do not store their key as a single field.
+///
+/// This is implemented for all types implementing [`ExtractKey`], and is what [`ExtractMap`] uses to find the key of
+/// a value, so composite or derived keys can be used without storing them in the value.
+///
+/// The same correctness requirements as [`ExtractKey`] apply, so the computed key must not change while the value
+/// is in the map, and computing it should be cheap, as it is done on every lookup collision and rehash.
+///
+/// # Examples
+/// ```
+/// use extract_map::{ComputeKey, ExtractMap};
+///
+/// struct Membership {
+///     tenant_id: u32,
+///     user_id: u32,
+///     role: &'static str,
+/// }
+///
+/// impl ComputeKey<(u32, u32)> for Membership {
+///     type Key<'a> = (u32, u32);
+///
+///     fn compute_key(&self) -> (u32, u32) {
+///         (self.tenant_id, self.user_id)
+///     }
+/// }
+///
+/// let mut memberships = ExtractMap::<(u32, u32), Membership>::new();
+/// memberships.insert(Membership { tenant_id: 1, user_id: 5, role: "admin" });
+/// memberships.insert(Membership { tenant_id: 2, user_id: 5, role: "viewer" });
+///
+/// assert_eq!(memberships.get(&(2, 5)).unwrap().role, "viewer");
+/// ```
+pub trait ComputeKey<K: Hash + Eq> {
+    /// The key returned by [`ComputeKey::compute_key`], which may be owned or borrowed from the value.
+    type Key<'a>: Borrow<K>
+    where
+        Self: 'a,
+        K: 'a;
+
+    /// Computes the key that this value should be referred to with.
+    fn compute_key(&self) -> Self::Key<'_>;
+}
+
+impl<K: Hash + Eq, V: ExtractKey<K> + ?Sized> ComputeKey<K> for V {
+    type Key<'a>
+        = &'a K
+    where
+        V: 'a,
+        K: 'a;
+
+    fn compute_key(&self) -> &K {
+        self.extract_key()
+    }
+}
+
 /// A hash map for memory efficent storage of value types which contain their own keys.
 ///
 /// This is backed by [`hashbrown::HashTable`], which is the backing storage for [`std`]'s [`HashSet`] and [`HashMap`].
@@ -53,21 +230,35 @@ pub trait ExtractKey<K: Hash + Eq> {
 /// The default hashing algorithm is the same as the standard library's hashing collections, [`RandomState`],
 /// although your own hasher can be provided via [`ExtractMap::with_hasher`] and it's similar methods.
 ///
+/// Keys are usually stored in the values and found via [`ExtractKey`], but keys made up of multiple fields, or
+/// otherwise computed from the value, can be used via [`ComputeKey`].
+///
+/// A custom [`Allocator`] from the re-exported [`allocator_api2`] can be provided via [`ExtractMap::with_hasher_in`]
+/// and [`ExtractMap::with_capacity_and_hasher_in`], such as an arena allocator.
+///
 /// [`HashSet`]: std::collections::HashSet
 /// [`HashMap`]: std::collections::HashMap
-pub struct ExtractMap<K, V, S = RandomState> {
+/// [`RandomState`]: std::collections::hash_map::RandomState
+pub struct ExtractMap<
+    K,
+    V,
+    #[cfg(feature = "std")] S = RandomState,
+    #[cfg(not(feature = "std"))] S,
+    A: Allocator = Global,
+> {
     // Any new fields added should be added to the `typesize` impl
-    table: hashbrown::HashTable<V>,
+    table: hashbrown::HashTable<V, A>,
     phantom: PhantomData<K>,
     build_hasher: S,
 }
 
-impl<K, V, S: Default> Default for ExtractMap<K, V, S> {
+impl<K, V, S: Default, A: Allocator + Default> Default for ExtractMap<K, V, S, A> {
     fn default() -> Self {
-        Self::with_hasher(S::default())
+        Self::with_hasher_in(S::default(), A::default())
     }
 }
 
+#[cfg(feature = "std")]
 impl<K, V> ExtractMap<K, V, RandomState> {
     /// Creates a new, empty [`ExtractMap`] with the [`RandomState`] hasher.
     #[must_use]
@@ -128,20 +319,63 @@ impl<K, V, S> ExtractMap<K, V, S> {
     }
 }
 
-impl<K, V, S> ExtractMap<K, V, S>
+impl<K, V, S, A: Allocator> ExtractMap<K, V, S, A> {
+    /// Creates a new, empty [`ExtractMap`] with the provided hasher and allocator.
+    #[must_use]
+    pub fn with_hasher_in(hash_builder: S, alloc: A) -> Self {
+        Self {
+            table: HashTable::new_in(alloc),
+            phantom: PhantomData,
+            build_hasher: hash_builder,
+        }
+    }
+
+    /// Creates a new [`ExtractMap`] with the provided hasher and allocator, and preallocated capacity.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// use extract_map::{allocator_api2::alloc::Global, ExtractMap, ExtractKey};
+    /// # use extract_map::doc_examples::User;
+    ///
+    /// let mut map = ExtractMap::<u64, User, _, _>::with_capacity_and_hasher_in(5, RandomState::new(), Global);
+    /// map.insert(User { id: 1, name: "Daisy" });
+    ///
+    /// assert_eq!(map.len(), 1);
+    /// assert!(map.capacity() >= 5);
+    /// ```
+    #[must_use]
+    pub fn with_capacity_and_hasher_in(capacity: usize, hash_builder: S, alloc: A) -> Self {
+        Self {
+            table: HashTable::with_capacity_in(capacity, alloc),
+            phantom: PhantomData,
+            build_hasher: hash_builder,
+        }
+    }
+}
+
+impl<K, V, S, A: Allocator> ExtractMap<K, V, S, A>
 where
     K: Hash + Eq,
-    V: ExtractKey<K>,
+    V: ComputeKey<K>,
     S: BuildHasher,
 {
-    fn raw_entry(&mut self, key: &K) -> RawEntry<'_, V> {
+    fn raw_entry<Q>(&mut self, key: &Q) -> RawEntry<'_, V, A>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
         self.table.entry(
             hash_one(&self.build_hasher, key),
-            |v| key == v.extract_key(),
-            |v| hash_one(&self.build_hasher, v.extract_key()),
+            |v| key.equivalent(v.compute_key().borrow()),
+            |v| hash_value(&self.build_hasher, v),
         )
     }
 
+    fn raw_entry_for(&mut self, value: &V) -> RawEntry<'_, V, A> {
+        self.raw_entry(value.compute_key().borrow())
+    }
+
     /// Inserts a value into the [`ExtractMap`].
     ///
     /// This extracts the key from the value using the [`ExtractKey`] trait, and therefore does not need a key to be provided.
@@ -158,7 +392,7 @@ where
     /// assert_eq!(map.len(), 2);
     /// ```
     pub fn insert(&mut self, value: V) -> Option<V> {
-        match self.raw_entry(value.extract_key()) {
+        match self.raw_entry_for(&value) {
             RawEntry::Occupied(entry) => Some(replace(entry.into_mut(), value)),
             RawEntry::Vacant(entry) => {
                 entry.insert(value);
@@ -167,6 +401,164 @@ where
         }
     }
 
+    /// Inserts a value into the [`ExtractMap`], without checking if a value with the same key already exists.
+    ///
+    /// This is faster than [`ExtractMap::insert`], so is useful for bulk loading values which are known to have unique keys.
+    ///
+    /// Inserting a value with a key already in the map will not cause memory unsafety, but the map will contain
+    /// duplicate keys, and which value is returned by lookups is unspecified.
+    ///
+    /// # Examples
+    /// ```
+    /// use extract_map::ExtractMap;
+    /// # use extract_map::doc_examples::User;
+    ///
+    /// let mut map = ExtractMap::new();
+    /// map.insert_unique_unchecked(User { id: 1, name: "Daisy" });
+    /// map.insert_unique_unchecked(User { id: 2, name: "Elliott" });
+    ///
+    /// assert_eq!(map.len(), 2);
+    /// assert_eq!(map.get(&2), Some(&User { id: 2, name: "Elliott" }));
+    /// ```
+    pub fn insert_unique_unchecked(&mut self, value: V) -> &mut V {
+        let build_hasher = &self.build_hasher;
+        let hash = hash_value(build_hasher, &value);
+
+        self.table
+            .insert_unique(hash, value, |v| hash_value(build_hasher, v))
+            .into_mut()
+    }
+
+    /// Retrieves a value from the [`ExtractMap`], inserting the result of the function if it is missing.
+    ///
+    /// The value returned from the function must have a key equivalent to the provided key.
+    ///
+    /// # Panics
+    /// Panics if the key of the value returned from the function is not equivalent to the provided key.
+    ///
+    /// # Examples
+    /// ```
+    /// use extract_map::ExtractMap;
+    /// # use extract_map::doc_examples::User;
+    ///
+    /// let mut map = ExtractMap::new();
+    /// map.insert(User { id: 1, name: "Daisy" });
+    ///
+    /// let daisy = map.get_or_insert_with(&1, |&id| User { id, name: "Elliott" });
+    /// assert_eq!(daisy, &User { id: 1, name: "Daisy" });
+    ///
+    /// let elliott = map.get_or_insert_with(&2, |&id| User { id, name: "Elliott" });
+    /// assert_eq!(elliott, &User { id: 2, name: "Elliott" });
+    /// ```
+    ///
+    /// Returning a value with a different key panics, as it could not be found by either key.
+    /// ```should_panic
+    /// use extract_map::ExtractMap;
+    /// # use extract_map::doc_examples::User;
+    ///
+    /// let mut map = ExtractMap::new();
+    /// map.get_or_insert_with(&1, |_| User { id: 99, name: "Elliott" });
+    /// ```
+    pub fn get_or_insert_with<Q>(&mut self, key: &Q, f: impl FnOnce(&Q) -> V) -> &V
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.raw_entry(key)
+            .or_insert_with(|| {
+                let value = f(key);
+                assert!(
+                    key.equivalent(value.compute_key().borrow()),
+                    "the key of the value returned from the function does not match the provided key"
+                );
+
+                value
+            })
+            .into_mut()
+    }
+
+    /// Reserves capacity for at least `additional` more values to be inserted without reallocating.
+    ///
+    /// # Panics
+    /// Panics if the new capacity overflows [`usize`].
+    ///
+    /// # Examples
+    /// ```
+    /// use extract_map::ExtractMap;
+    /// # use extract_map::doc_examples::User;
+    ///
+    /// let mut map: ExtractMap<u64, User> = ExtractMap::new();
+    /// map.reserve(10);
+    ///
+    /// assert!(map.capacity() >= 10);
+    /// ```
+    pub fn reserve(&mut self, additional: usize) {
+        let build_hasher = &self.build_hasher;
+        self.table
+            .reserve(additional, |v| hash_value(build_hasher, v));
+    }
+
+    /// Tries to reserve capacity for at least `additional` more values to be inserted without reallocating.
+    ///
+    /// # Errors
+    /// Errors if the new capacity overflows [`usize`], or the allocator reports a failure.
+    ///
+    /// # Examples
+    /// ```
+    /// use extract_map::ExtractMap;
+    /// # use extract_map::doc_examples::User;
+    ///
+    /// let mut map: ExtractMap<u64, User> = ExtractMap::new();
+    ///
+    /// assert!(map.try_reserve(10).is_ok());
+    /// assert!(map.try_reserve(usize::MAX).is_err());
+    /// ```
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        let build_hasher = &self.build_hasher;
+        self.table
+            .try_reserve(additional, |v| hash_value(build_hasher, v))
+    }
+
+    /// Shrinks the capacity of the [`ExtractMap`] as much as possible, while keeping the current values.
+    ///
+    /// # Examples
+    /// ```
+    /// use extract_map::ExtractMap;
+    /// # use extract_map::doc_examples::User;
+    ///
+    /// let mut map = ExtractMap::with_capacity(100);
+    /// map.insert(User { id: 1, name: "Daisy" });
+    /// map.shrink_to_fit();
+    ///
+    /// assert!(map.capacity() < 100);
+    /// assert!(map.contains_key(&1));
+    /// ```
+    pub fn shrink_to_fit(&mut self) {
+        let build_hasher = &self.build_hasher;
+        self.table.shrink_to_fit(|v| hash_value(build_hasher, v));
+    }
+
+    /// Shrinks the capacity of the [`ExtractMap`] to at least `min_capacity`, while keeping the current values.
+    ///
+    /// If the current capacity is less than `min_capacity`, this does nothing.
+    ///
+    /// # Examples
+    /// ```
+    /// use extract_map::ExtractMap;
+    /// # use extract_map::doc_examples::User;
+    ///
+    /// let mut map = ExtractMap::with_capacity(100);
+    /// map.insert(User { id: 1, name: "Daisy" });
+    /// map.shrink_to(10);
+    ///
+    /// assert!(map.capacity() >= 10 && map.capacity() < 100);
+    /// assert!(map.contains_key(&1));
+    /// ```
+    pub fn shrink_to(&mut self, min_capacity: usize) {
+        let build_hasher = &self.build_hasher;
+        self.table
+            .shrink_to(min_capacity, |v| hash_value(build_hasher, v));
+    }
+
     /// Removes a value from the [`ExtractMap`].
     ///
     /// # Examples
@@ -181,9 +573,14 @@ where
     /// assert_eq!(map.remove(&1), Some(user));
     /// assert!(map.is_empty())
     /// ```
-    pub fn remove(&mut self, key: &K) -> Option<V> {
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
         let hash = hash_one(&self.build_hasher, key);
-        let entry = self.table.find_entry(hash, |v| key == v.extract_key());
+        let entry = self
+            .table
+            .find_entry(hash, |v| key.equivalent(v.compute_key().borrow()));
 
         match entry {
             Ok(entry) => Some(entry.remove().0),
@@ -193,32 +590,301 @@ where
 
     /// Checks if a value is in the [`ExtractMap`].
     #[must_use]
-    pub fn contains_key(&self, key: &K) -> bool {
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
         self.get(key).is_some()
     }
 
     /// Retrieves a value from the [`ExtractMap`].
+    ///
+    /// The key may be any borrowed form of the map's key type, as long as [`Hash`] and [`Eq`] on the borrowed form
+    /// match those for the key type, in the same way as [`HashMap::get`].
+    ///
+    /// # Examples
+    /// ```
+    /// use extract_map::{ExtractMap, ExtractKey};
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// struct Channel {
+    ///     name: String,
+    ///     topic: &'static str,
+    /// }
+    ///
+    /// impl ExtractKey<String> for Channel {
+    ///     fn extract_key(&self) -> &String {
+    ///         &self.name
+    ///     }
+    /// }
+    ///
+    /// let mut map = ExtractMap::new();
+    /// map.insert(Channel { name: String::from("general"), topic: "Anything goes" });
+    ///
+    /// // No need to allocate a `String` to look up the channel.
+    /// assert_eq!(map.get("general").map(|c| c.topic), Some("Anything goes"));
+    /// assert!(map.get("random").is_none());
+    /// ```
+    ///
+    /// [`HashMap::get`]: std::collections::HashMap::get
     #[must_use]
-    pub fn get(&self, key: &K) -> Option<&V> {
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
         let hash = hash_one(&self.build_hasher, key);
-        self.table.find(hash, |v| key == v.extract_key())
+        self.table
+            .find(hash, |v| key.equivalent(v.compute_key().borrow()))
     }
 
     /// Retrieves a mutable guard to a value in the [`ExtractMap`].
     ///
-    /// This guard is required as the current implementation takes the value out
-    /// of the map and reinserts on Drop to allow mutation of the key field.
+    /// This guard is required to allow mutation of the key field. The value is mutated in place, and
+    /// on Drop the guard checks if the key has changed, only moving the value if it has.
+    ///
+    /// If the key is changed to that of another value in the map, that value is replaced, even if both
+    /// keys have the same hash.
+    ///
+    /// See [`MutGuard`] for rolling back changes and controlling what happens if a panic occurs.
+    ///
+    /// # Examples
+    /// ```
+    /// use extract_map::{ExtractMap, ExtractKey};
+    /// # use extract_map::doc_examples::User;
+    ///
+    /// let mut map = ExtractMap::new();
+    /// map.insert(User { id: 1, name: "Daisy" });
+    ///
+    /// // Mutating a non-key field is done in place.
+    /// map.get_mut(&1).unwrap().name = "Elliott";
+    /// assert_eq!(map.get(&1), Some(&User { id: 1, name: "Elliott" }));
+    ///
+    /// // Mutating the key field moves the value on Drop.
+    /// map.get_mut(&1).unwrap().id = 2;
+    /// assert_eq!(map.get(&1), None);
+    /// assert_eq!(map.get(&2), Some(&User { id: 2, name: "Elliott" }));
+    ///
+    /// // Keys are compared rather than hashes, so this also holds when every hash collides.
+    /// # use extract_map::doc_examples::CollidingState;
+    /// let mut map: ExtractMap<u64, User, CollidingState> = ExtractMap::default();
+    /// map.insert(User { id: 1, name: "Daisy" });
+    /// map.insert(User { id: 2, name: "Elliott" });
+    ///
+    /// map.get_mut(&1).unwrap().id = 2;
+    /// assert_eq!(map.get(&2), Some(&User { id: 2, name: "Daisy" }));
+    /// assert_eq!(map.len(), 1);
+    /// assert!(map.check_integrity().is_valid());
+    /// ```
     #[must_use]
-    pub fn get_mut<'a>(&'a mut self, key: &K) -> Option<MutGuard<'a, K, V, S>> {
-        let value = self.remove(key)?;
-        Some(MutGuard {
-            value: ManuallyDrop::new(value),
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<MutGuard<'_, K, V, S, A>>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let hash = hash_one(&self.build_hasher, key);
+        let entry = self
+            .table
+            .find_entry(hash, |v| key.equivalent(v.compute_key().borrow()))
+            .ok()?;
+
+        Some(MutGuard::new(entry, &self.build_hasher, hash))
+    }
+
+    /// Retrieves mutable references to `N` values in the [`ExtractMap`] at once.
+    ///
+    /// Returns [`None`] if any of the keys are missing.
+    ///
+    /// Unlike [`ExtractMap::get_mut`], these references are not guarded, so the key field must not be mutated
+    /// through them. Doing so will cause the values to be stored under the wrong hash, and lookups to fail.
+    ///
+    /// # Panics
+    /// Panics if any of the keys overlap.
+    ///
+    /// # Examples
+    /// ```
+    /// use extract_map::ExtractMap;
+    /// # use extract_map::doc_examples::User;
+    ///
+    /// let mut map = ExtractMap::new();
+    /// map.insert(User { id: 1, name: "Daisy" });
+    /// map.insert(User { id: 2, name: "Elliott" });
+    ///
+    /// let [daisy, elliott] = map.get_many_mut([&1, &2]).unwrap();
+    /// std::mem::swap(&mut daisy.name, &mut elliott.name);
+    ///
+    /// assert_eq!(map.get(&1), Some(&User { id: 1, name: "Elliott" }));
+    /// assert_eq!(map.get(&2), Some(&User { id: 2, name: "Daisy" }));
+    ///
+    /// // Missing keys result in None.
+    /// assert!(map.get_many_mut([&1, &3]).is_none());
+    /// ```
+    pub fn get_many_mut<Q, const N: usize>(&mut self, keys: [&Q; N]) -> Option<[&mut V; N]>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let hashes = keys.map(|key| hash_one(&self.build_hasher, key));
+        all_some(
+            self.table
+                .get_many_mut(hashes, |i, v| keys[i].equivalent(v.compute_key().borrow())),
+        )
+    }
+
+    /// Retrieves mutable references to `N` values in the [`ExtractMap`] at once, without checking
+    /// that the keys do not overlap.
+    ///
+    /// Returns [`None`] if any of the keys are missing.
+    ///
+    /// For a safe alternative, see [`ExtractMap::get_many_mut`], which also documents the restrictions on
+    /// mutating the key field.
+    ///
+    /// # Safety
+    /// Calling this method with overlapping keys is *[undefined behavior]*, even if the resulting references
+    /// are not used.
+    ///
+    /// [undefined behavior]: https://doc.rust-lang.org/reference/behavior-considered-undefined.html
+    pub unsafe fn get_many_unchecked_mut<Q, const N: usize>(
+        &mut self,
+        keys: [&Q; N],
+    ) -> Option<[&mut V; N]>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let hashes = keys.map(|key| hash_one(&self.build_hasher, key));
+
+        // SAFETY: The caller guarantees that the keys do not overlap.
+        let values = unsafe {
+            self.table
+                .get_many_unchecked_mut(hashes, |i, v| keys[i].equivalent(v.compute_key().borrow()))
+        };
+
+        all_some(values)
+    }
+
+    /// Retains only the values specified by the predicate, allowing mutation of the key field.
+    ///
+    /// Any retained value whose key has changed is moved to the correct position after all values have been
+    /// visited, replacing any other value with the same key. This is also done if `f` panics, including for the
+    /// value `f` was called with. This has the cost of cloning and hashing every key, so [`ExtractMap::retain`]
+    /// should be preferred if the key is not mutated.
+    ///
+    /// # Examples
+    /// ```
+    /// use extract_map::ExtractMap;
+    /// # use extract_map::doc_examples::User;
+    ///
+    /// let mut map: ExtractMap<u64, User> = (0..6).map(|id| User { id, name: "Daisy" }).collect();
+    ///
+    /// // Remove the odd users, and give the even users new IDs.
+    /// map.retain_mut(|user| {
+    ///     user.id += 10;
+    ///     user.id % 2 == 0
+    /// });
+    ///
+    /// assert_eq!(map.len(), 3);
+    /// assert!(map.contains_key(&10) && map.contains_key(&12) && map.contains_key(&14));
+    /// assert!(!map.contains_key(&0));
+    /// ```
+    ///
+    /// Values are still moved if `f` panics, and keys are compared rather than hashes.
+    /// ```
+    /// use std::panic::{catch_unwind, AssertUnwindSafe};
+    ///
+    /// use extract_map::ExtractMap;
+    /// # use extract_map::doc_examples::{CollidingState, User};
+    ///
+    /// let mut map: ExtractMap<u64, User, CollidingState> = (0..10).map(|id| User { id, name: "Daisy" }).collect();
+    ///
+    /// let mut calls = 0;
+    /// let result = catch_unwind(AssertUnwindSafe(|| {
+    ///     map.retain_mut(|user| {
+    ///         calls += 1;
+    ///         user.id += 100;
+    ///         assert!(calls < 8);
+    ///         true
+    ///     });
+    /// }));
+    ///
+    /// assert!(result.is_err());
+    /// assert_eq!(map.len(), 10);
+    /// assert_eq!(map.iter().filter(|user| user.id >= 100).count(), 8);
+    /// assert!(map.check_integrity().is_valid());
+    ///
+    /// // Renaming a value to the key of another value replaces it, even if their hashes are equal.
+    /// map.retain_mut(|user| {
+    ///     if user.id == 101 {
+    ///         user.id = 102;
+    ///         user.name = "Elliott";
+    ///     }
+    ///     true
+    /// });
+    ///
+    /// assert_eq!(map.len(), 9);
+    /// assert_eq!(map.get(&102).unwrap().name, "Elliott");
+    /// assert!(map.check_integrity().is_valid());
+    /// ```
+    pub fn retain_mut(&mut self, mut f: impl FnMut(&mut V) -> bool)
+    where
+        K: Clone,
+    {
+        let mut guard = RetainGuard {
             map: self,
-        })
+            moved: Vec::new(),
+        };
+
+        let RetainGuard { map, moved } = &mut guard;
+        let build_hasher = &map.build_hasher;
+        map.table.retain(|value| {
+            let old_key: K = value.compute_key().borrow().clone();
+            let address: *const V = value;
+
+            // Recorded before calling `f`, so the value is still moved if `f` panics after changing its key.
+            moved.push((hash_one(build_hasher, &old_key), address));
+            let keep = f(value);
+
+            if !keep || *value.compute_key().borrow() == old_key {
+                moved.pop();
+            }
+
+            keep
+        });
     }
 }
 
-impl<K, V, S> ExtractMap<K, V, S> {
+/// Moves the values whose key was changed by [`ExtractMap::retain_mut`] on Drop, so they are moved even if the
+/// predicate panics.
+struct RetainGuard<'a, K, V, S, A>
+where
+    K: Hash + Eq,
+    V: ComputeKey<K>,
+    S: BuildHasher,
+    A: Allocator,
+{
+    map: &'a mut ExtractMap<K, V, S, A>,
+    /// The hash of the old key and the address of each value to move, which [`HashTable::retain`] does not change.
+    moved: Vec<(u64, *const V)>,
+}
+
+impl<K, V, S, A> Drop for RetainGuard<'_, K, V, S, A>
+where
+    K: Hash + Eq,
+    V: ComputeKey<K>,
+    S: BuildHasher,
+    A: Allocator,
+{
+    fn drop(&mut self) {
+        // Every value is removed before any are inserted, as inserting may replace or reallocate the others.
+        let table = &mut self.map.table;
+        let values: Vec<V> = (self.moved.drain(..))
+            .filter_map(|(hash, value)| {
+                let entry = table.find_entry(hash, |v| core::ptr::eq(v, value)).ok()?;
+                Some(entry.remove().0)
+            })
+            .collect();
+
+        self.map.extend(values);
+    }
+}
+
+impl<K, V, S, A: Allocator> ExtractMap<K, V, S, A> {
     /// Retrieves the number of remaining values that can be inserted before a reallocation.
     #[must_use]
     pub fn capacity(&self) -> usize {
@@ -237,6 +903,12 @@ impl<K, V, S> ExtractMap<K, V, S> {
         self.table.is_empty()
     }
 
+    /// Returns a reference to the allocator used by the [`ExtractMap`].
+    #[must_use]
+    pub fn allocator(&self) -> &A {
+        self.table.allocator()
+    }
+
     /// Returns the total amount of memory allocated internally, in bytes.
     ///
     /// The returned number is informational only. It is intended to be
@@ -257,14 +929,107 @@ impl<K, V, S> ExtractMap<K, V, S> {
     /// Retrieves a iterator over mutable borrowed values.
     ///
     /// If you need an iterator over the keys and values, simply use [`ExtractKey`], but do not mutate the key.
+    /// If you need to mutate the keys, use [`ExtractMap::iter_mut_rekey`].
     ///
     /// Use [`IntoIterator::into_iter`] for an iterator over owned values.
     pub fn iter_mut(&mut self) -> iter::IterMut<'_, V> {
         self.into_iter()
     }
+
+    /// Removes all values from the [`ExtractMap`], keeping the allocated memory for reuse.
+    ///
+    /// # Examples
+    /// ```
+    /// use extract_map::ExtractMap;
+    /// # use extract_map::doc_examples::User;
+    ///
+    /// let mut map = ExtractMap::new();
+    /// map.insert(User { id: 1, name: "Daisy" });
+    ///
+    /// let capacity = map.capacity();
+    /// map.clear();
+    ///
+    /// assert!(map.is_empty());
+    /// assert_eq!(map.capacity(), capacity);
+    /// ```
+    pub fn clear(&mut self) {
+        self.table.clear();
+    }
+
+    /// Removes all values from the [`ExtractMap`] as an iterator, keeping the allocated memory for reuse.
+    ///
+    /// If the iterator is dropped before being fully consumed, the remaining values are dropped.
+    ///
+    /// # Examples
+    /// ```
+    /// use extract_map::ExtractMap;
+    /// # use extract_map::doc_examples::User;
+    ///
+    /// let mut map = ExtractMap::new();
+    /// map.insert(User { id: 1, name: "Daisy" });
+    /// map.insert(User { id: 2, name: "Elliott" });
+    ///
+    /// let capacity = map.capacity();
+    /// let mut ids: Vec<u64> = map.drain().map(|user| user.id).collect();
+    /// ids.sort_unstable();
+    ///
+    /// assert_eq!(ids, [1, 2]);
+    /// assert!(map.is_empty());
+    /// assert_eq!(map.capacity(), capacity);
+    /// ```
+    pub fn drain(&mut self) -> iter::Drain<'_, V, A> {
+        iter::Drain(self.table.drain())
+    }
+
+    /// Retains only the values specified by the predicate.
+    ///
+    /// If the key needs to be mutated while filtering, use [`ExtractMap::retain_mut`].
+    ///
+    /// # Examples
+    /// ```
+    /// use extract_map::ExtractMap;
+    /// # use extract_map::doc_examples::User;
+    ///
+    /// let mut map: ExtractMap<u64, User> = (0..6).map(|id| User { id, name: "Daisy" }).collect();
+    /// map.retain(|user| user.id % 2 == 0);
+    ///
+    /// assert_eq!(map.len(), 3);
+    /// assert!(map.iter().all(|user| user.id % 2 == 0));
+    /// ```
+    pub fn retain(&mut self, mut f: impl FnMut(&V) -> bool) {
+        self.table.retain(|v| f(v));
+    }
+
+    /// Creates an iterator which removes and yields every value matching the predicate.
+    ///
+    /// Values are only removed as the iterator is consumed, and values not yet visited when the
+    /// iterator is dropped are kept in the map.
+    ///
+    /// The predicate is given a mutable reference to the value, but the key must not be mutated
+    /// for any value which is kept in the map.
+    ///
+    /// # Examples
+    /// ```
+    /// use extract_map::ExtractMap;
+    /// # use extract_map::doc_examples::User;
+    ///
+    /// let mut map: ExtractMap<u64, User> = (0..6).map(|id| User { id, name: "Daisy" }).collect();
+    ///
+    /// let mut odd: Vec<u64> = map.extract_if(|user| user.id % 2 == 1).map(|user| user.id).collect();
+    /// odd.sort_unstable();
+    ///
+    /// assert_eq!(odd, [1, 3, 5]);
+    /// assert_eq!(map.len(), 3);
+    /// ```
+    pub fn extract_if<F>(&mut self, f: F) -> iter::ExtractIf<'_, V, F, A>
+    where
+        F: FnMut(&mut V) -> bool,
+    {
+        iter::ExtractIf(self.table.extract_if(f))
+    }
 }
 
-impl<K, V: Clone, S: Clone> Clone for ExtractMap<K, V, S> {
+impl<K, V: Clone, S: Clone, A: Allocator + Clone> Clone for ExtractMap<K, V, S, A> {
     fn clone(&self) -> Self {
         Self {
             build_hasher: self.build_hasher.clone(),
@@ -279,22 +1044,25 @@ impl<K, V: Clone, S: Clone> Clone for ExtractMap<K, V, S> {
     }
 }
 
-impl<K, V, S> Debug for ExtractMap<K, V, S>
+impl<K, V, S, A: Allocator> Debug for ExtractMap<K, V, S, A>
 where
     K: Debug + Hash + Eq,
-    V: Debug + ExtractKey<K>,
+    V: Debug + ComputeKey<K>,
 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_map()
-            .entries(self.iter().map(|v| (v.extract_key(), v)))
-            .finish()
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let mut map = f.debug_map();
+        for value in self {
+            map.entry(value.compute_key().borrow(), value);
+        }
+
+        map.finish()
     }
 }
 
-impl<K, V, S> PartialEq for ExtractMap<K, V, S>
+impl<K, V, S, A: Allocator> PartialEq for ExtractMap<K, V, S, A>
 where
     K: Hash + Eq,
-    V: ExtractKey<K> + PartialEq,
+    V: ComputeKey<K> + PartialEq,
     S: BuildHasher,
 {
     fn eq(&self, other: &Self) -> bool {
@@ -303,25 +1071,27 @@ where
         }
 
         self.iter().all(|v| {
-            let k = v.extract_key();
+            let k = v.compute_key();
 
-            other.get(k).is_some_and(|other_v| {
-                let other_k = other_v.extract_key();
-                k == other_k && v == other_v
+            other.get(k.borrow()).is_some_and(|other_v| {
+                let other_k = other_v.compute_key();
+                k.borrow() == other_k.borrow() && v == other_v
             })
         })
     }
 }
 
-impl<K, V, S> FromIterator<V> for ExtractMap<K, V, S>
+impl<K, V, S, A: Allocator> FromIterator<V> for ExtractMap<K, V, S, A>
 where
     K: Hash + Eq,
-    V: ExtractKey<K>,
+    V: ComputeKey<K>,
     S: BuildHasher + Default,
+    A: Allocator + Default,
 {
     fn from_iter<T: IntoIterator<Item = V>>(iter: T) -> Self {
         let iter = iter.into_iter();
-        let mut this = Self::with_capacity_and_hasher(iter.size_hint().0, S::default());
+        let mut this =
+            Self::with_capacity_and_hasher_in(iter.size_hint().0, S::default(), A::default());
 
         for value in iter {
             this.insert(value);
@@ -331,10 +1101,10 @@ where
     }
 }
 
-impl<K, V, S> Extend<V> for ExtractMap<K, V, S>
+impl<K, V, S, A: Allocator> Extend<V> for ExtractMap<K, V, S, A>
 where
     K: Hash + Eq,
-    V: ExtractKey<K>,
+    V: ComputeKey<K>,
     S: BuildHasher,
 {
     fn extend<T: IntoIterator<Item = V>>(&mut self, iter: T) {
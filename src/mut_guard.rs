@@ -1,55 +1,283 @@
-use std::{
+//! A guard for mutating values in an [`ExtractMap`], returned from [`ExtractMap::get_mut`].
+
+use core::{
     hash::{BuildHasher, Hash},
+    marker::PhantomData,
     mem::ManuallyDrop,
     ops::{Deref, DerefMut},
+    ptr,
 };
 
-use crate::{ExtractKey, ExtractMap};
+use allocator_api2::alloc::{Allocator, Global};
+use hashbrown::hash_table::{Entry as RawEntry, OccupiedEntry as RawOccupiedEntry};
+
+use crate::{has_duplicate_key, hash_value, keys_match, ComputeKey};
+
+#[cfg(doc)]
+use crate::ExtractMap;
+
+#[cfg(feature = "std")]
+fn panicking() -> bool {
+    std::thread::panicking()
+}
+
+/// Without `std` there is no way to detect unwinding, so the [`UnwindPolicy`] is never applied.
+#[cfg(not(feature = "std"))]
+fn panicking() -> bool {
+    false
+}
+
+/// What a [`MutGuard`] should do with the value if it is dropped while unwinding from a panic.
+///
+/// This is set with [`MutGuard::set_unwind_policy`], and requires the `std` feature to have any effect.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UnwindPolicy {
+    /// Keeps any changes made to the value, as if [`MutGuard::commit`] was called.
+    #[default]
+    Commit,
+    /// Restores the snapshot taken with [`MutGuard::snapshot`], or removes the value if no snapshot was taken.
+    Rollback,
+    /// Removes the value from the map, as if [`MutGuard::remove`] was called.
+    Remove,
+}
 
-pub struct MutGuard<'a, K, V, S>
+/// A guard allowing mutation of a value in an [`ExtractMap`], including the key field.
+///
+/// The value is mutated in place, and when the guard is dropped or [committed](MutGuard::commit) its key is
+/// checked. If the key has changed, the value is moved to match it, replacing any other value with the same key.
+/// A value whose key is unchanged is left in place.
+pub struct MutGuard<'a, K, V, S, A = Global>
 where
     K: Hash + Eq,
-    V: ExtractKey<K>,
+    V: ComputeKey<K>,
     S: BuildHasher,
+    A: Allocator,
 {
-    pub(crate) value: ManuallyDrop<V>,
-    pub(crate) map: &'a mut ExtractMap<K, V, S>,
+    pub(crate) entry: ManuallyDrop<RawOccupiedEntry<'a, V, A>>,
+    pub(crate) build_hasher: &'a S,
+    /// The hash of the key when the guard was created, used to check if the value has to be moved.
+    pub(crate) hash: u64,
+    pub(crate) snapshot: Option<V>,
+    pub(crate) unwind_policy: UnwindPolicy,
+    /// If the guard was created while already panicking, in which case the [`UnwindPolicy`] is not applied.
+    pub(crate) created_while_panicking: bool,
+    pub(crate) phantom: PhantomData<K>,
 }
 
-impl<K, V, S> Drop for MutGuard<'_, K, V, S>
+impl<'a, K, V, S, A: Allocator> MutGuard<'a, K, V, S, A>
 where
     K: Hash + Eq,
-    V: ExtractKey<K>,
+    V: ComputeKey<K>,
+    S: BuildHasher,
+{
+    pub(crate) fn new(entry: RawOccupiedEntry<'a, V, A>, build_hasher: &'a S, hash: u64) -> Self {
+        Self {
+            entry: ManuallyDrop::new(entry),
+            build_hasher,
+            hash,
+            snapshot: None,
+            unwind_policy: UnwindPolicy::default(),
+            created_while_panicking: panicking(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Takes a snapshot of the current value, which is restored by [`MutGuard::rollback`] and [`UnwindPolicy::Rollback`].
+    ///
+    /// Taking another snapshot replaces the previous one.
+    pub fn snapshot(&mut self)
+    where
+        V: Clone,
+    {
+        self.snapshot = Some(self.entry.get().clone());
+    }
+
+    /// Sets what should happen to the value if the guard is dropped while unwinding from a panic.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::panic::{catch_unwind, AssertUnwindSafe};
+    ///
+    /// use extract_map::{ExtractMap, mut_guard::UnwindPolicy};
+    /// # use extract_map::doc_examples::User;
+    ///
+    /// let mut map = ExtractMap::new();
+    /// map.insert(User { id: 1, name: "Daisy" });
+    /// map.insert(User { id: 2, name: "Elliott" });
+    ///
+    /// // With `Rollback`, the snapshot is restored if a panic occurs.
+    /// let result = catch_unwind(AssertUnwindSafe(|| {
+    ///     let mut guard = map.get_mut(&1).unwrap();
+    ///     guard.snapshot();
+    ///     guard.set_unwind_policy(UnwindPolicy::Rollback);
+    ///
+    ///     guard.id = 3;
+    ///     panic!("Something went wrong!");
+    /// }));
+    ///
+    /// assert!(result.is_err());
+    /// assert_eq!(map.get(&1), Some(&User { id: 1, name: "Daisy" }));
+    /// assert_eq!(map.get(&3), None);
+    ///
+    /// // With `Remove`, the half-mutated value is taken out of the map.
+    /// let result = catch_unwind(AssertUnwindSafe(|| {
+    ///     let mut guard = map.get_mut(&2).unwrap();
+    ///     guard.set_unwind_policy(UnwindPolicy::Remove);
+    ///
+    ///     guard.name = "Half-written";
+    ///     panic!("Something went wrong!");
+    /// }));
+    ///
+    /// assert!(result.is_err());
+    /// assert_eq!(map.get(&2), None);
+    /// assert_eq!(map.len(), 1);
+    /// ```
+    pub fn set_unwind_policy(&mut self, policy: UnwindPolicy) {
+        self.unwind_policy = policy;
+    }
+
+    /// Keeps the changes made to the value, moving it if the key has changed.
+    ///
+    /// This is equivalent to dropping the guard, but makes the intent explicit.
+    pub fn commit(self) {
+        drop(self);
+    }
+
+    /// Discards the changes made to the value, restoring the snapshot taken with [`MutGuard::snapshot`].
+    ///
+    /// # Panics
+    /// Panics if no snapshot has been taken.
+    ///
+    /// # Examples
+    /// ```
+    /// use extract_map::ExtractMap;
+    /// # use extract_map::doc_examples::User;
+    ///
+    /// let mut map = ExtractMap::new();
+    /// map.insert(User { id: 1, name: "Daisy" });
+    ///
+    /// let mut guard = map.get_mut(&1).unwrap();
+    /// guard.snapshot();
+    /// guard.id = 2;
+    /// guard.name = "Elliott";
+    /// guard.rollback();
+    ///
+    /// assert_eq!(map.get(&1), Some(&User { id: 1, name: "Daisy" }));
+    /// assert_eq!(map.len(), 1);
+    /// ```
+    #[track_caller]
+    pub fn rollback(mut self) {
+        let snapshot = self
+            .snapshot
+            .take()
+            .expect("MutGuard::rollback called without a snapshot");
+
+        *self.entry.get_mut() = snapshot;
+    }
+
+    /// Removes the value from the map, discarding any snapshot.
+    ///
+    /// # Examples
+    /// ```
+    /// use extract_map::ExtractMap;
+    /// # use extract_map::doc_examples::User;
+    ///
+    /// let mut map = ExtractMap::new();
+    /// map.insert(User { id: 1, name: "Daisy" });
+    ///
+    /// let mut guard = map.get_mut(&1).unwrap();
+    /// guard.name = "Elliott";
+    ///
+    /// assert_eq!(guard.remove(), User { id: 1, name: "Elliott" });
+    /// assert!(map.is_empty());
+    /// ```
+    #[allow(clippy::must_use_candidate)]
+    pub fn remove(mut self) -> V {
+        // SAFETY: The ManuallyDrop is never used again, as self is forgotten below.
+        let entry = unsafe { ManuallyDrop::take(&mut self.entry) };
+        drop(self.snapshot.take());
+
+        // The remaining fields do not need dropping, and Drop would reinsert the value.
+        core::mem::forget(self);
+        entry.remove().0
+    }
+}
+
+impl<K, V, S, A: Allocator> Drop for MutGuard<'_, K, V, S, A>
+where
+    K: Hash + Eq,
+    V: ComputeKey<K>,
     S: BuildHasher,
 {
     fn drop(&mut self) {
         // SAFETY: The ManuallyDrop is never used again as we are in Drop.
-        let value = unsafe { ManuallyDrop::take(&mut self.value) };
+        let mut entry = unsafe { ManuallyDrop::take(&mut self.entry) };
+
+        if panicking() && !self.created_while_panicking {
+            match (self.unwind_policy, self.snapshot.take()) {
+                (UnwindPolicy::Commit, _) => {}
+                (UnwindPolicy::Rollback, Some(snapshot)) => *entry.get_mut() = snapshot,
+                (UnwindPolicy::Rollback | UnwindPolicy::Remove, _) => {
+                    entry.remove();
+                    return;
+                }
+            }
+        }
+
+        let new_hash = hash_value(self.build_hasher, entry.get());
+        let (value, vacant) = if new_hash == self.hash {
+            // The value is still in the correct bucket, but the key may have changed to that of another value
+            // with a colliding hash, which has to be replaced.
+            let current: *const V = entry.get();
+            let table = entry.into_table();
+            if !has_duplicate_key(table, new_hash, current) {
+                return;
+            }
+
+            let Ok(entry) = table.find_entry(new_hash, |v| ptr::eq(v, current)) else {
+                unreachable!("the guarded value should still be in the table")
+            };
+
+            entry.remove()
+        } else {
+            entry.remove()
+        };
+
+        let raw_entry = vacant.into_table().entry(
+            new_hash,
+            |v| keys_match(&value, v),
+            |v| hash_value(self.build_hasher, v),
+        );
 
-        self.map.insert(value);
+        match raw_entry {
+            RawEntry::Occupied(mut entry) => *entry.get_mut() = value,
+            RawEntry::Vacant(entry) => {
+                entry.insert(value);
+            }
+        }
     }
 }
 
-impl<K, V, S> Deref for MutGuard<'_, K, V, S>
+impl<K, V, S, A: Allocator> Deref for MutGuard<'_, K, V, S, A>
 where
     K: Hash + Eq,
-    V: ExtractKey<K>,
+    V: ComputeKey<K>,
     S: BuildHasher,
 {
     type Target = V;
 
     fn deref(&self) -> &Self::Target {
-        &self.value
+        self.entry.get()
     }
 }
 
-impl<K, V, S> DerefMut for MutGuard<'_, K, V, S>
+impl<K, V, S, A: Allocator> DerefMut for MutGuard<'_, K, V, S, A>
 where
     K: Hash + Eq,
-    V: ExtractKey<K>,
+    V: ComputeKey<K>,
     S: BuildHasher,
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.value
+        self.entry.get_mut()
     }
 }
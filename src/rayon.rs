@@ -0,0 +1,173 @@
+//! Parallel iterators for [`ExtractMap`], using [`rayon`].
+//!
+//! These are not usually named directly, instead being created via the traits in [`rayon::prelude`].
+#![allow(clippy::module_name_repetitions)]
+
+use alloc::vec::Vec;
+use core::hash::{BuildHasher, Hash};
+
+use allocator_api2::alloc::{Allocator, Global};
+use rayon::iter::{
+    plumbing::UnindexedConsumer, FromParallelIterator, IntoParallelIterator, ParallelExtend,
+    ParallelIterator,
+};
+
+use crate::{ComputeKey, ExtractMap};
+
+/// A parallel iterator over borrowed values, created by [`IntoParallelRefIterator::par_iter`](::rayon::iter::IntoParallelRefIterator::par_iter).
+///
+/// # Example
+/// ```
+/// use extract_map::ExtractMap;
+/// use rayon::prelude::*;
+/// # use extract_map::doc_examples::User;
+///
+/// let map: ExtractMap<u64, User> = (0..100).map(|id| User { id, name: "Elliott" }).collect();
+///
+/// let id_sum: u64 = map.par_iter().map(|user| user.id).sum();
+/// assert_eq!(id_sum, (0..100).sum());
+/// ```
+#[must_use = "Iterators do nothing if not consumed"]
+pub struct ParIter<'a, V>(hashbrown::hash_table::rayon::ParIter<'a, V>);
+
+impl<'a, V: Sync> ParallelIterator for ParIter<'a, V> {
+    type Item = &'a V;
+
+    fn drive_unindexed<C: UnindexedConsumer<Self::Item>>(self, consumer: C) -> C::Result {
+        self.0.drive_unindexed(consumer)
+    }
+}
+
+impl<V> Clone for ParIter<'_, V> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<V: core::fmt::Debug> core::fmt::Debug for ParIter<'_, V> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// A parallel iterator over mutably borrowed values, created by [`IntoParallelRefMutIterator::par_iter_mut`](::rayon::iter::IntoParallelRefMutIterator::par_iter_mut).
+///
+/// As with [`ExtractMap::iter_mut`], the keys of the values must not be mutated.
+/// If you need to mutate the keys, use [`ExtractMap::iter_mut_rekey`].
+///
+/// # Example
+/// ```
+/// use extract_map::ExtractMap;
+/// use rayon::prelude::*;
+/// # use extract_map::doc_examples::User;
+///
+/// let mut map: ExtractMap<u64, User> = (0..100).map(|id| User { id, name: "Elliott" }).collect();
+///
+/// map.par_iter_mut().for_each(|user| user.name = "Daisy");
+/// assert!(map.iter().all(|user| user.name == "Daisy"));
+/// ```
+#[must_use = "Iterators do nothing if not consumed"]
+pub struct ParIterMut<'a, V>(hashbrown::hash_table::rayon::ParIterMut<'a, V>);
+
+impl<'a, V: Send> ParallelIterator for ParIterMut<'a, V> {
+    type Item = &'a mut V;
+
+    fn drive_unindexed<C: UnindexedConsumer<Self::Item>>(self, consumer: C) -> C::Result {
+        self.0.drive_unindexed(consumer)
+    }
+}
+
+impl<V: core::fmt::Debug> core::fmt::Debug for ParIterMut<'_, V> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// A parallel iterator over owned values, created by [`IntoParallelIterator::into_par_iter`].
+#[must_use = "Iterators do nothing if not consumed"]
+pub struct IntoParIter<V, A: Allocator = Global>(hashbrown::hash_table::rayon::IntoParIter<V, A>);
+
+impl<V: Send, A: Allocator + Send> ParallelIterator for IntoParIter<V, A> {
+    type Item = V;
+
+    fn drive_unindexed<C: UnindexedConsumer<Self::Item>>(self, consumer: C) -> C::Result {
+        self.0.drive_unindexed(consumer)
+    }
+}
+
+impl<V: core::fmt::Debug, A: Allocator> core::fmt::Debug for IntoParIter<V, A> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<'a, K, V: Sync, S, A: Allocator> IntoParallelIterator for &'a ExtractMap<K, V, S, A> {
+    type Item = &'a V;
+    type Iter = ParIter<'a, V>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        ParIter((&self.table).into_par_iter())
+    }
+}
+
+impl<'a, K, V: Send, S, A: Allocator> IntoParallelIterator for &'a mut ExtractMap<K, V, S, A> {
+    type Item = &'a mut V;
+    type Iter = ParIterMut<'a, V>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        ParIterMut((&mut self.table).into_par_iter())
+    }
+}
+
+impl<K, V: Send, S, A: Allocator + Send> IntoParallelIterator for ExtractMap<K, V, S, A> {
+    type Item = V;
+    type Iter = IntoParIter<V, A>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        IntoParIter(self.table.into_par_iter())
+    }
+}
+
+/// Collects the values in parallel, then inserts them into the map.
+///
+/// # Example
+/// ```
+/// use extract_map::ExtractMap;
+/// use rayon::prelude::*;
+/// # use extract_map::doc_examples::User;
+///
+/// let map: ExtractMap<u64, User> = (0..100_u64)
+///     .into_par_iter()
+///     .map(|id| User { id, name: "Elliott" })
+///     .collect();
+///
+/// assert_eq!(map.len(), 100);
+/// ```
+impl<K, V, S, A> FromParallelIterator<V> for ExtractMap<K, V, S, A>
+where
+    K: Hash + Eq,
+    V: ComputeKey<K> + Send,
+    S: BuildHasher + Default,
+    A: Allocator + Default,
+{
+    fn from_par_iter<I: IntoParallelIterator<Item = V>>(par_iter: I) -> Self {
+        let values: Vec<V> = par_iter.into_par_iter().collect();
+        values.into_iter().collect()
+    }
+}
+
+/// Collects the values in parallel, then inserts them into the map.
+impl<K, V, S, A> ParallelExtend<V> for ExtractMap<K, V, S, A>
+where
+    K: Hash + Eq,
+    V: ComputeKey<K> + Send,
+    S: BuildHasher,
+    A: Allocator,
+{
+    fn par_extend<I: IntoParallelIterator<Item = V>>(&mut self, par_iter: I) {
+        let values: Vec<V> = par_iter.into_par_iter().collect();
+
+        self.reserve(values.len());
+        self.extend(values);
+    }
+}
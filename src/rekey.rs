@@ -0,0 +1,244 @@
+//! Bulk mutation of keys in an [`ExtractMap`], returned from [`ExtractMap::iter_mut_rekey`].
+
+use alloc::vec::Vec;
+use core::{
+    borrow::Borrow,
+    hash::{BuildHasher, Hash},
+};
+
+use allocator_api2::alloc::{Allocator, Global};
+use hashbrown::hash_table::Entry as RawEntry;
+
+use crate::{iter, ComputeKey, ExtractMap};
+
+/// What a [`RekeyGuard`] should do if a value's new key is already used by another value.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RekeyPolicy {
+    /// Removes the moved value from the map, returning it in a [`RekeyError`] from [`RekeyGuard::finish`].
+    ///
+    /// If the guard is dropped instead, this behaves the same as [`RekeyPolicy::KeepFirst`].
+    #[default]
+    Error,
+    /// Replaces the value already using the key with the moved value.
+    LastWins,
+    /// Keeps the value already using the key, dropping the moved value.
+    KeepFirst,
+}
+
+/// The error returned from [`RekeyGuard::finish`] if any values could not be moved with [`RekeyPolicy::Error`].
+#[derive(Debug)]
+pub struct RekeyError<V> {
+    /// The values which could not be moved, as another value already used their new key.
+    pub collisions: Vec<V>,
+}
+
+impl<V> core::fmt::Display for RekeyError<V> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{} value(s) could not be moved, as their new key was already in use",
+            self.collisions.len()
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl<V: core::fmt::Debug> std::error::Error for RekeyError<V> {}
+
+/// A guard allowing mutation of the keys of every value in an [`ExtractMap`].
+///
+/// Every key is cloned when the guard is created, and when the guard is dropped or [finished](RekeyGuard::finish)
+/// every value whose key has changed is moved to the correct position.
+#[must_use = "Dropping the guard discards values whose new key is already in use, use `RekeyGuard::finish` to retrieve them"]
+pub struct RekeyGuard<'a, K, V, S, A = Global>
+where
+    K: Hash + Eq,
+    V: ComputeKey<K>,
+    S: BuildHasher,
+    A: Allocator,
+{
+    map: &'a mut ExtractMap<K, V, S, A>,
+    /// The keys when the guard was created, in iteration order.
+    old_keys: Vec<K>,
+    policy: RekeyPolicy,
+}
+
+impl<K, V, S, A: Allocator> ExtractMap<K, V, S, A>
+where
+    K: Hash + Eq,
+    V: ComputeKey<K>,
+    S: BuildHasher,
+{
+    /// Retrieves a guard allowing mutation of the keys of every value, unlike [`ExtractMap::iter_mut`].
+    ///
+    /// This has the cost of cloning every key, and allocating to store the original keys.
+    ///
+    /// # Examples
+    /// ```
+    /// use extract_map::{ExtractMap, rekey::RekeyPolicy};
+    /// # use extract_map::doc_examples::User;
+    ///
+    /// let mut map = ExtractMap::new();
+    /// map.insert(User { id: 1, name: "Daisy" });
+    /// map.insert(User { id: 2, name: "Elliott" });
+    ///
+    /// let mut guard = map.iter_mut_rekey(RekeyPolicy::Error);
+    /// for user in &mut guard {
+    ///     user.id += 10;
+    /// }
+    ///
+    /// assert!(guard.finish().is_ok());
+    /// assert_eq!(map.get(&11), Some(&User { id: 11, name: "Daisy" }));
+    /// assert_eq!(map.get(&12), Some(&User { id: 12, name: "Elliott" }));
+    /// ```
+    pub fn iter_mut_rekey(&mut self, policy: RekeyPolicy) -> RekeyGuard<'_, K, V, S, A>
+    where
+        K: Clone,
+    {
+        let old_keys = self
+            .table
+            .iter()
+            .map(|v| v.compute_key().borrow().clone())
+            .collect();
+
+        RekeyGuard {
+            map: self,
+            old_keys,
+            policy,
+        }
+    }
+}
+
+impl<K, V, S, A: Allocator> RekeyGuard<'_, K, V, S, A>
+where
+    K: Hash + Eq,
+    V: ComputeKey<K>,
+    S: BuildHasher,
+{
+    /// Retrieves an iterator over mutable borrowed values, which may have their keys mutated.
+    pub fn iter_mut(&mut self) -> iter::IterMut<'_, V> {
+        self.map.iter_mut()
+    }
+
+    /// Moves every value whose key has changed, returning any values which collided with [`RekeyPolicy::Error`].
+    ///
+    /// # Errors
+    /// Errors if the policy is [`RekeyPolicy::Error`] and a value's new key was already in use, returning
+    /// the values which could not be moved.
+    ///
+    /// # Examples
+    /// ```
+    /// use extract_map::{ExtractMap, rekey::RekeyPolicy};
+    /// # use extract_map::doc_examples::User;
+    ///
+    /// let mut map = ExtractMap::new();
+    /// map.insert(User { id: 1, name: "Daisy" });
+    /// map.insert(User { id: 2, name: "Elliott" });
+    ///
+    /// let mut guard = map.iter_mut_rekey(RekeyPolicy::Error);
+    /// guard.iter_mut().find(|user| user.id == 2).unwrap().id = 1;
+    ///
+    /// let err = guard.finish().unwrap_err();
+    /// assert_eq!(err.collisions, [User { id: 1, name: "Elliott" }]);
+    /// assert_eq!(map.get(&1), Some(&User { id: 1, name: "Daisy" }));
+    /// assert_eq!(map.len(), 1);
+    ///
+    /// // With `LastWins`, the moved value replaces the existing one.
+    /// map.insert(User { id: 2, name: "Elliott" });
+    ///
+    /// let mut guard = map.iter_mut_rekey(RekeyPolicy::LastWins);
+    /// guard.iter_mut().find(|user| user.id == 2).unwrap().id = 1;
+    /// drop(guard);
+    ///
+    /// assert_eq!(map.get(&1), Some(&User { id: 1, name: "Elliott" }));
+    /// assert_eq!(map.len(), 1);
+    /// ```
+    ///
+    /// Keys are compared rather than hashes, so collisions are still found when every hash collides.
+    /// ```
+    /// use extract_map::{ExtractMap, rekey::RekeyPolicy};
+    /// # use extract_map::doc_examples::{CollidingState, User};
+    ///
+    /// let mut map: ExtractMap<u64, User, CollidingState> = ExtractMap::default();
+    /// map.insert(User { id: 1, name: "Daisy" });
+    /// map.insert(User { id: 2, name: "Elliott" });
+    ///
+    /// let mut guard = map.iter_mut_rekey(RekeyPolicy::Error);
+    /// guard.iter_mut().find(|user| user.id == 1).unwrap().id = 2;
+    ///
+    /// let err = guard.finish().unwrap_err();
+    /// assert_eq!(err.collisions, [User { id: 2, name: "Daisy" }]);
+    /// assert!(map.check_integrity().is_valid());
+    /// ```
+    pub fn finish(mut self) -> Result<(), RekeyError<V>> {
+        let collisions = self.relocate(self.policy);
+        if collisions.is_empty() {
+            Ok(())
+        } else {
+            Err(RekeyError { collisions })
+        }
+    }
+
+    fn relocate(&mut self, policy: RekeyPolicy) -> Vec<V> {
+        let old_keys = core::mem::take(&mut self.old_keys);
+        if old_keys.is_empty() {
+            return Vec::new();
+        }
+
+        // Values cannot be inserted or removed while the guard is held, so the
+        // iteration order is the same as when the keys were recorded.
+        let mut old_keys = old_keys.into_iter();
+        let moved: Vec<V> = self
+            .map
+            .table
+            .extract_if(|v| old_keys.next().as_ref() != Some(Borrow::<K>::borrow(&v.compute_key())))
+            .collect();
+
+        let mut collisions = Vec::new();
+        for value in moved {
+            match self.map.raw_entry_for(&value) {
+                RawEntry::Occupied(mut entry) => match policy {
+                    RekeyPolicy::Error => collisions.push(value),
+                    RekeyPolicy::LastWins => *entry.get_mut() = value,
+                    RekeyPolicy::KeepFirst => {}
+                },
+                RawEntry::Vacant(entry) => {
+                    entry.insert(value);
+                }
+            }
+        }
+
+        collisions
+    }
+}
+
+impl<K, V, S, A: Allocator> Drop for RekeyGuard<'_, K, V, S, A>
+where
+    K: Hash + Eq,
+    V: ComputeKey<K>,
+    S: BuildHasher,
+{
+    fn drop(&mut self) {
+        // The collisions cannot be returned, so the values already using the keys are kept.
+        let policy = match self.policy {
+            RekeyPolicy::Error => RekeyPolicy::KeepFirst,
+            policy => policy,
+        };
+
+        self.relocate(policy);
+    }
+}
+
+impl<'a, K, V, S, A: Allocator> IntoIterator for &'a mut RekeyGuard<'_, K, V, S, A>
+where
+    K: Hash + Eq,
+    V: ComputeKey<K>,
+    S: BuildHasher,
+{
+    type Item = &'a mut V;
+    type IntoIter = iter::IterMut<'a, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
@@ -1,101 +1,843 @@
-use std::{
+use alloc::{
+    string::{String, ToString as _},
+    vec::Vec,
+};
+use core::{
+    borrow::Borrow,
+    fmt::Debug,
     hash::{BuildHasher, Hash},
     marker::PhantomData,
 };
 
-use crate::{ExtractKey, ExtractMap};
+use allocator_api2::alloc::{Allocator, Global};
+use hashbrown::hash_table::Entry as RawEntry;
+use serde::de::{DeserializeSeed, MapAccess, SeqAccess};
+
+use crate::{ComputeKey, ExtractMap};
+
+use content::{Content, ContentDeserializer};
+
+mod content;
+
+/// The maximum capacity to preallocate from a size hint, which may come from untrusted input.
+const MAX_PREALLOCATION: usize = 4096;
+
+/// How values with duplicate keys are handled when deserializing an [`ExtractMap`] with an [`ExtractMapSeed`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// Later values replace earlier values with the same key, the same as [`ExtractMap::insert`].
+    #[default]
+    LastWins,
+    /// Later values with the same key as an earlier value are dropped.
+    KeepFirst,
+    /// Deserialization fails with an error including the duplicated key.
+    Error,
+}
+
+/// An element which was skipped while deserializing a [`LenientMap`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElementError<K> {
+    /// The position of the element in the input sequence or map.
+    pub index: usize,
+    /// The key of the map entry, or [`None`] if the map was deserialized from a sequence.
+    pub key: Option<K>,
+    /// The message of the error which caused the element to be skipped.
+    pub message: String,
+}
+
+impl<K> ElementError<K> {
+    fn new(index: usize, key: Option<K>, error: &impl core::fmt::Display) -> Self {
+        Self {
+            index,
+            key,
+            message: error.to_string(),
+        }
+    }
+}
+
+impl<K: Debug> core::fmt::Display for ElementError<K> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match &self.key {
+            Some(key) => write!(
+                f,
+                "element {} (key `{key:?}`): {}",
+                self.index, self.message
+            ),
+            None => write!(f, "element {}: {}", self.index, self.message),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K: Debug> std::error::Error for ElementError<K> {}
+
+/// Inserts deserialized values into an [`ExtractMap`], handling duplicate keys.
+trait InsertPolicy<K, V> {
+    fn insert<S, A, E>(&self, map: &mut ExtractMap<K, V, S, A>, value: V) -> Result<(), E>
+    where
+        K: Hash + Eq,
+        V: ComputeKey<K>,
+        S: BuildHasher,
+        A: Allocator,
+        E: serde::de::Error;
+}
+
+/// The policy used by the [`serde::Deserialize`] implementation, which does not require `K: Debug`.
+struct Replace;
+
+impl<K, V> InsertPolicy<K, V> for Replace {
+    fn insert<S, A, E>(&self, map: &mut ExtractMap<K, V, S, A>, value: V) -> Result<(), E>
+    where
+        K: Hash + Eq,
+        V: ComputeKey<K>,
+        S: BuildHasher,
+        A: Allocator,
+        E: serde::de::Error,
+    {
+        map.insert(value);
+        Ok(())
+    }
+}
+
+impl<K: Debug, V> InsertPolicy<K, V> for DuplicatePolicy {
+    fn insert<S, A, E>(&self, map: &mut ExtractMap<K, V, S, A>, value: V) -> Result<(), E>
+    where
+        K: Hash + Eq,
+        V: ComputeKey<K>,
+        S: BuildHasher,
+        A: Allocator,
+        E: serde::de::Error,
+    {
+        match (map.raw_entry_for(&value), self) {
+            (RawEntry::Vacant(entry), _) => {
+                entry.insert(value);
+            }
+            (RawEntry::Occupied(mut entry), DuplicatePolicy::LastWins) => *entry.get_mut() = value,
+            (RawEntry::Occupied(_), DuplicatePolicy::KeepFirst) => {}
+            (RawEntry::Occupied(_), DuplicatePolicy::Error) => {
+                let key = value.compute_key();
+                let key: &K = key.borrow();
+                return Err(E::custom(format_args!("duplicate key `{key:?}`")));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+struct SeqVisitor<'a, K, V, S, A: Allocator, P, VS> {
+    map: &'a mut ExtractMap<K, V, S, A>,
+    policy: P,
+    value_seed: VS,
+    /// If set, elements which fail to deserialize or insert are skipped and their errors recorded here.
+    errors: Option<&'a mut Vec<ElementError<K>>>,
+}
+
+impl<'de, K, V, S, A, P, VS> serde::de::Visitor<'de> for SeqVisitor<'_, K, V, S, A, P, VS>
+where
+    K: Hash + Eq,
+    V: ComputeKey<K>,
+    S: BuildHasher,
+    A: Allocator,
+    P: InsertPolicy<K, V>,
+    VS: DeserializeSeed<'de, Value = V> + Clone,
+{
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        formatter.write_str("a sequence")
+    }
+
+    fn visit_seq<M: SeqAccess<'de>>(self, mut seq: M) -> Result<Self::Value, M::Error> {
+        self.map
+            .reserve(seq.size_hint().unwrap_or_default().min(MAX_PREALLOCATION));
+
+        let Some(errors) = self.errors else {
+            while let Some(value) = seq.next_element_seed(self.value_seed.clone())? {
+                self.policy.insert(self.map, value)?;
+            }
+
+            return Ok(());
+        };
+
+        // Each element is buffered first, so the input can still be read after a value fails to deserialize.
+        let mut index = 0;
+        while let Some(content) = seq.next_element::<Content<'de>>()? {
+            let result = (self.value_seed.clone())
+                .deserialize(ContentDeserializer::<M::Error>::new(content))
+                .and_then(|value| self.policy.insert(self.map, value));
+
+            if let Err(err) = result {
+                errors.push(ElementError::new(index, None, &err));
+            }
+
+            index += 1;
+        }
+
+        Ok(())
+    }
+}
+
+struct MapVisitor<'a, K, V, S, A: Allocator, P, VS> {
+    map: &'a mut ExtractMap<K, V, S, A>,
+    /// If the keys of the map should be checked against the keys of the values.
+    strict: bool,
+    policy: P,
+    value_seed: VS,
+    /// If set, entries whose values fail to deserialize or insert are skipped and their errors recorded here.
+    errors: Option<&'a mut Vec<ElementError<K>>>,
+}
+
+impl<K, V, S, A, P, VS> MapVisitor<'_, K, V, S, A, P, VS>
+where
+    K: Hash + Eq,
+    V: ComputeKey<K>,
+    S: BuildHasher,
+    A: Allocator,
+    P: InsertPolicy<K, V>,
+{
+    fn insert<E: serde::de::Error>(&mut self, key: &K, value: V) -> Result<(), E> {
+        if self.strict && *key != *value.compute_key().borrow() {
+            return Err(E::custom(
+                "the key of a map entry does not match the key of its value",
+            ));
+        }
+
+        self.policy.insert(self.map, value)
+    }
+}
+
+impl<'de, K, V, S, A, P, VS> serde::de::Visitor<'de> for MapVisitor<'_, K, V, S, A, P, VS>
+where
+    K: Hash + Eq + serde::Deserialize<'de>,
+    V: ComputeKey<K>,
+    S: BuildHasher,
+    A: Allocator,
+    P: InsertPolicy<K, V>,
+    VS: DeserializeSeed<'de, Value = V> + Clone,
+{
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        formatter.write_str("a map")
+    }
+
+    fn visit_map<M: MapAccess<'de>>(mut self, mut map: M) -> Result<Self::Value, M::Error> {
+        self.map
+            .reserve(map.size_hint().unwrap_or_default().min(MAX_PREALLOCATION));
+
+        let Some(errors) = self.errors.take() else {
+            while let Some(key) = map.next_key::<K>()? {
+                let value = map.next_value_seed(self.value_seed.clone())?;
+                self.insert(&key, value)?;
+            }
+
+            return Ok(());
+        };
+
+        // The keys are not buffered, as formats such as JSON can only deserialize non-string keys as map keys.
+        let mut index = 0;
+        while let Some(key) = map.next_key::<K>()? {
+            let content = map.next_value::<Content<'de>>()?;
+            let result = (self.value_seed.clone())
+                .deserialize(ContentDeserializer::<M::Error>::new(content))
+                .and_then(|value| self.insert(&key, value));
+
+            if let Err(err) = result {
+                errors.push(ElementError::new(index, Some(key), &err));
+            }
+
+            index += 1;
+        }
+
+        Ok(())
+    }
+}
+
+/// A [`DeserializeSeed`] for an [`ExtractMap`], allowing configuration of how it is deserialized.
+///
+/// By default, this behaves the same as the [`serde::Deserialize`] implementation on [`ExtractMap`].
+///
+/// # Examples
+/// ```
+/// use extract_map::{DuplicatePolicy, ExtractMap, ExtractMapSeed};
+/// use serde::de::DeserializeSeed;
+/// # use extract_map::doc_examples::User;
+///
+/// let json = r#"[{"id": 0, "name": "Elliott"}, {"id": 0, "name": "Daisy"}]"#;
+///
+/// let seed = ExtractMapSeed::new().duplicate_policy(DuplicatePolicy::KeepFirst);
+/// let map: ExtractMap<u64, User> = seed.deserialize(&mut serde_json::Deserializer::from_str(json)).unwrap();
+/// assert_eq!(map.get(&0).unwrap().name, "Elliott");
+///
+/// let seed = ExtractMapSeed::<u64, User>::new().duplicate_policy(DuplicatePolicy::Error);
+/// let err = seed.deserialize(&mut serde_json::Deserializer::from_str(json)).unwrap_err();
+/// assert!(err.to_string().starts_with("duplicate key `0`"));
+/// ```
+///
+/// A hasher which does not implement [`Default`] can be provided, as can a seed to deserialize the values with.
+/// ```
+/// use std::collections::hash_map::DefaultHasher;
+/// use std::hash::{BuildHasher, Hasher};
+///
+/// use extract_map::{ExtractMap, ExtractMapSeed};
+/// use serde::de::{Deserialize, DeserializeSeed, Deserializer};
+/// # use extract_map::doc_examples::User;
+///
+/// struct KeyedState(u64);
+///
+/// impl BuildHasher for KeyedState {
+///     type Hasher = DefaultHasher;
+///
+///     fn build_hasher(&self) -> DefaultHasher {
+///         let mut hasher = DefaultHasher::new();
+///         hasher.write_u64(self.0);
+///         hasher
+///     }
+/// }
+///
+/// #[derive(Clone)]
+/// struct OffsetSeed(u64);
+///
+/// impl<'de> DeserializeSeed<'de> for OffsetSeed {
+///     type Value = User;
+///
+///     fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<User, D::Error> {
+///         let user = User::deserialize(deserializer)?;
+///         Ok(User { id: user.id + self.0, ..user })
+///     }
+/// }
+///
+/// let json = r#"[{"id": 0, "name": "Elliott"}, {"id": 1, "name": "Daisy"}]"#;
+///
+/// let seed = ExtractMapSeed::with_hasher(KeyedState(42)).capacity(16).value_seed(OffsetSeed(100));
+/// let map = seed.deserialize(&mut serde_json::Deserializer::from_str(json)).unwrap();
+///
+/// assert_eq!(map.get(&101).unwrap().name, "Daisy");
+/// assert!(map.capacity() >= 16);
+/// ```
+///
+/// The keys are only deserialized in [`FromMap`] mode, so deserializing from a sequence does not require
+/// `K: Deserialize`, which is useful for keys computed by [`ComputeKey`].
+/// ```
+/// use extract_map::{ComputeKey, ExtractMap, ExtractMapSeed};
+/// use serde::de::DeserializeSeed;
+///
+/// #[derive(Debug, PartialEq, Eq, Hash)]
+/// struct Pair(u32, u32);
+///
+/// #[derive(serde::Deserialize)]
+/// struct Membership {
+///     tenant_id: u32,
+///     user_id: u32,
+/// }
+///
+/// impl ComputeKey<Pair> for Membership {
+///     type Key<'a> = Pair;
+///
+///     fn compute_key(&self) -> Pair {
+///         Pair(self.tenant_id, self.user_id)
+///     }
+/// }
+///
+/// let json = r#"[{"tenant_id": 1, "user_id": 5}, {"tenant_id": 2, "user_id": 5}]"#;
+///
+/// let seed = ExtractMapSeed::<Pair, Membership>::new();
+/// let map = seed.deserialize(&mut serde_json::Deserializer::from_str(json)).unwrap();
+/// assert!(map.contains_key(&Pair(2, 5)));
+/// ```
+///
+/// [`DeserializeSeed`]: serde::de::DeserializeSeed
+#[cfg(feature = "serde")]
+pub struct ExtractMapSeed<
+    K,
+    V,
+    #[cfg(feature = "std")] S = std::collections::hash_map::RandomState,
+    #[cfg(not(feature = "std"))] S,
+    A: Allocator = Global,
+    VS = PhantomData<V>,
+    F = FromSeq,
+> {
+    build_hasher: S,
+    alloc: A,
+    capacity: usize,
+    duplicate_policy: DuplicatePolicy,
+    value_seed: VS,
+    phantom: PhantomData<(K, V, F)>,
+}
+
+/// The input format of an [`ExtractMapSeed`] which deserializes from a sequence of values, the default.
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FromSeq;
+
+/// The input format of an [`ExtractMapSeed`] which deserializes from a map of keys to values, set by
+/// [`ExtractMapSeed::from_map`].
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FromMap;
+
+#[cfg(feature = "serde")]
+impl<K, V, S: Default, A: Allocator + Default> ExtractMapSeed<K, V, S, A> {
+    /// Creates a new [`ExtractMapSeed`], which deserializes from a sequence and keeps the last of any duplicates.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_hasher_in(S::default(), A::default())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<K, V, S> ExtractMapSeed<K, V, S> {
+    /// Creates a new [`ExtractMapSeed`], which deserializes into a map using the provided hasher.
+    #[must_use]
+    pub fn with_hasher(build_hasher: S) -> Self {
+        Self::with_hasher_in(build_hasher, Global)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<K, V, S, A: Allocator> ExtractMapSeed<K, V, S, A> {
+    /// Creates a new [`ExtractMapSeed`], which deserializes into a map using the provided hasher and allocator.
+    #[must_use]
+    pub fn with_hasher_in(build_hasher: S, alloc: A) -> Self {
+        Self {
+            build_hasher,
+            alloc,
+            capacity: 0,
+            duplicate_policy: DuplicatePolicy::default(),
+            value_seed: PhantomData,
+            phantom: PhantomData,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<K, V, S, A: Allocator, VS> ExtractMapSeed<K, V, S, A, VS> {
+    /// Deserializes from a map of keys to values instead of a sequence of values, discarding the keys.
+    ///
+    /// This matches the format of [`serde_as_map`](crate::serde_as_map), and requires `K: Deserialize`.
+    #[must_use]
+    pub fn from_map(self) -> ExtractMapSeed<K, V, S, A, VS, FromMap> {
+        ExtractMapSeed {
+            build_hasher: self.build_hasher,
+            alloc: self.alloc,
+            capacity: self.capacity,
+            duplicate_policy: self.duplicate_policy,
+            value_seed: self.value_seed,
+            phantom: PhantomData,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<K, V, S, A: Allocator, VS, F> ExtractMapSeed<K, V, S, A, VS, F> {
+    /// Sets the minimum capacity of the deserialized map, which is otherwise based on the size hint of the input.
+    #[must_use]
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
 
-pub(crate) struct WithSizeHint<I> {
-    inner: I,
-    hint: Option<usize>,
+    /// Sets how values with duplicate keys are handled.
+    #[must_use]
+    pub fn duplicate_policy(mut self, duplicate_policy: DuplicatePolicy) -> Self {
+        self.duplicate_policy = duplicate_policy;
+        self
+    }
+
+    /// Skips elements which fail to deserialize or insert, deserializing into a [`LenientMap`] with their errors.
+    ///
+    /// See [`LenientMap`] for the limitations of this mode.
+    #[must_use]
+    pub fn lenient(self) -> LenientSeed<K, V, S, A, VS, F> {
+        LenientSeed(self)
+    }
+
+    /// Sets the [`DeserializeSeed`] used to deserialize each value, instead of [`serde::Deserialize`].
+    ///
+    /// [`DeserializeSeed`]: serde::de::DeserializeSeed
+    #[must_use]
+    pub fn value_seed<NewVS>(self, value_seed: NewVS) -> ExtractMapSeed<K, V, S, A, NewVS, F> {
+        ExtractMapSeed {
+            build_hasher: self.build_hasher,
+            alloc: self.alloc,
+            capacity: self.capacity,
+            duplicate_policy: self.duplicate_policy,
+            value_seed,
+            phantom: PhantomData,
+        }
+    }
 }
 
-impl<Item, I: Iterator<Item = Item>> Iterator for WithSizeHint<I> {
-    type Item = Item;
+#[cfg(feature = "serde")]
+impl<K, V, S: Default, A: Allocator + Default> Default for ExtractMapSeed<K, V, S, A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        self.inner.next()
+#[cfg(feature = "serde")]
+impl<K, V, S, A, VS, F> Clone for ExtractMapSeed<K, V, S, A, VS, F>
+where
+    S: Clone,
+    A: Allocator + Clone,
+    VS: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            build_hasher: self.build_hasher.clone(),
+            alloc: self.alloc.clone(),
+            capacity: self.capacity,
+            duplicate_policy: self.duplicate_policy,
+            value_seed: self.value_seed.clone(),
+            phantom: PhantomData,
+        }
     }
+}
 
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        (self.hint.unwrap_or_default(), self.hint)
+#[cfg(feature = "serde")]
+impl<K, V, S, A: Allocator, VS, F> Debug for ExtractMapSeed<K, V, S, A, VS, F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ExtractMapSeed")
+            .field("capacity", &self.capacity)
+            .field("duplicate_policy", &self.duplicate_policy)
+            .finish_non_exhaustive()
     }
 }
 
-pub(crate) trait IteratorExt: Iterator + Sized {
-    fn with_size_hint(self, hint: Option<usize>) -> WithSizeHint<Self>;
+#[cfg(feature = "serde")]
+impl<'de, K, V, S, A, VS> DeserializeSeed<'de> for ExtractMapSeed<K, V, S, A, VS, FromSeq>
+where
+    K: Debug + Hash + Eq,
+    V: ComputeKey<K>,
+    S: BuildHasher,
+    A: Allocator,
+    VS: DeserializeSeed<'de, Value = V> + Clone,
+{
+    type Value = ExtractMap<K, V, S, A>;
+
+    fn deserialize<D: serde::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        let mut map =
+            ExtractMap::with_capacity_and_hasher_in(self.capacity, self.build_hasher, self.alloc);
+
+        deserializer.deserialize_seq(SeqVisitor {
+            map: &mut map,
+            policy: self.duplicate_policy,
+            value_seed: self.value_seed,
+            errors: None,
+        })?;
+
+        Ok(map)
+    }
 }
 
-impl<Item, I: Iterator<Item = Item>> IteratorExt for I {
-    fn with_size_hint(self, hint: Option<usize>) -> WithSizeHint<I> {
-        WithSizeHint { inner: self, hint }
+#[cfg(feature = "serde")]
+impl<'de, K, V, S, A, VS> DeserializeSeed<'de> for ExtractMapSeed<K, V, S, A, VS, FromMap>
+where
+    K: Debug + Hash + Eq + serde::Deserialize<'de>,
+    V: ComputeKey<K>,
+    S: BuildHasher,
+    A: Allocator,
+    VS: DeserializeSeed<'de, Value = V> + Clone,
+{
+    type Value = ExtractMap<K, V, S, A>;
+
+    fn deserialize<D: serde::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        let mut map =
+            ExtractMap::with_capacity_and_hasher_in(self.capacity, self.build_hasher, self.alloc);
+
+        deserializer.deserialize_map(MapVisitor {
+            map: &mut map,
+            strict: false,
+            policy: self.duplicate_policy,
+            value_seed: self.value_seed,
+            errors: None,
+        })?;
+
+        Ok(map)
     }
 }
 
-/// Deserializes an [`ExtractMap`] from either a sequence or a map.
+/// An [`ExtractMap`] deserialized leniently, alongside the errors of any elements which were skipped.
+///
+/// The [`serde::Deserialize`] implementation reads a sequence of values, the same as [`ExtractMap`]. To
+/// configure the hasher, duplicate policy or map format, use [`ExtractMapSeed::lenient`].
 ///
-/// This uses [`serde::Deserializer::deserialize_any`], so may fail for formats which are not self-describing.
+/// Each element is buffered before being deserialized, so the rest of the input can still be read if it
+/// fails. This requires a self-describing format such as JSON, and input which is invalid in the format
+/// itself, such as malformed JSON, still fails deserialization. When deserializing from a map, the keys are
+/// not buffered and must still deserialize successfully.
 ///
 /// # Example
 /// ```
-/// use extract_map::{ExtractMap, ExtractKey};
+/// use extract_map::{ExtractMap, LenientMap};
 /// # use extract_map::doc_examples::User;
 ///
-/// let map_json = r#"{"0": {"id": 0, "name": "Elliott"}, "1": {"id": 1, "name": "Daisy"}}"#;
-/// let seq_json = r#"[{"id": 0, "name": "Elliott"}, {"id": 1, "name": "Daisy"}]"#;
+/// let json = r#"[{"id": 0, "name": "Elliott"}, {"id": "one", "name": "Daisy"}, {"id": 2, "name": "Cat"}]"#;
+/// let LenientMap { map, errors }: LenientMap<u64, User> = serde_json::from_str(json).unwrap();
+///
+/// assert_eq!(map.len(), 2);
+/// assert_eq!(map.get(&2).unwrap().name, "Cat");
 ///
-/// let map: ExtractMap<u64, User> = serde_json::from_str(map_json).unwrap();
-/// let seq: ExtractMap<u64, User> = serde_json::from_str(seq_json).unwrap();
+/// assert_eq!(errors.len(), 1);
+/// assert_eq!(errors[0].index, 1);
+/// assert_eq!(errors[0].message, r#"invalid type: string "one", expected u64"#);
+/// ```
 ///
-/// assert_eq!(map, seq);
+/// When deserializing from a map, the key of each skipped entry is kept, and mismatched or duplicate keys
+/// are recorded instead of failing.
 /// ```
+/// use extract_map::{DuplicatePolicy, ExtractMap, ExtractMapSeed};
+/// use serde::de::DeserializeSeed;
+/// # use extract_map::doc_examples::User;
+///
+/// let json = r#"{"0": {"id": 0, "name": "Elliott"}, "1": {"id": 1, "name": 5}, "2": {"id": 0, "name": "Daisy"}}"#;
+///
+/// let seed = ExtractMapSeed::<u64, User>::new().from_map().duplicate_policy(DuplicatePolicy::Error).lenient();
+/// let lenient = seed.deserialize(&mut serde_json::Deserializer::from_str(json)).unwrap();
+///
+/// assert_eq!(lenient.map.get(&0).unwrap().name, "Elliott");
+/// assert_eq!(lenient.errors.len(), 2);
+/// assert!(lenient.errors[0].to_string().starts_with("element 1 (key `1`): invalid type: integer `5`"));
+/// assert_eq!(lenient.errors[1].key, Some(2));
+/// ```
+#[cfg(feature = "serde")]
+pub struct LenientMap<
+    K,
+    V,
+    #[cfg(feature = "std")] S = std::collections::hash_map::RandomState,
+    #[cfg(not(feature = "std"))] S,
+    A: Allocator = Global,
+> {
+    /// The map of the elements which were deserialized and inserted successfully.
+    pub map: ExtractMap<K, V, S, A>,
+    /// The errors of the elements which were skipped, in input order.
+    pub errors: Vec<ElementError<K>>,
+}
+
 #[cfg(feature = "serde")]
-impl<'de, K, V, S> serde::Deserialize<'de> for ExtractMap<K, V, S>
+impl<K, V, S, A: Allocator> Debug for LenientMap<K, V, S, A>
+where
+    K: Debug + Hash + Eq,
+    V: Debug + ComputeKey<K>,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("LenientMap")
+            .field("map", &self.map)
+            .field("errors", &self.errors)
+            .finish()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, K, V, S, A> serde::Deserialize<'de> for LenientMap<K, V, S, A>
 where
     K: Hash + Eq,
-    V: ExtractKey<K> + serde::Deserialize<'de>,
+    V: ComputeKey<K> + serde::Deserialize<'de>,
     S: BuildHasher + Default,
+    A: Allocator + Default,
 {
     fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
-        use serde::de::{IgnoredAny, MapAccess, SeqAccess};
+        let mut map = ExtractMap::default();
+        let mut errors = Vec::new();
 
-        struct Visitor<K, V, S>(PhantomData<(K, V, S)>);
+        deserializer.deserialize_seq(SeqVisitor {
+            map: &mut map,
+            policy: Replace,
+            value_seed: PhantomData,
+            errors: Some(&mut errors),
+        })?;
 
-        impl<'de, K, V, S> serde::de::Visitor<'de> for Visitor<K, V, S>
-        where
-            K: Hash + Eq,
-            V: ExtractKey<K> + serde::Deserialize<'de>,
-            S: BuildHasher + Default,
-        {
-            type Value = ExtractMap<K, V, S>;
+        Ok(Self { map, errors })
+    }
+}
 
-            fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-                formatter.write_str("a sequence")
-            }
+/// A [`DeserializeSeed`] for a [`LenientMap`], created by [`ExtractMapSeed::lenient`].
+///
+/// [`DeserializeSeed`]: serde::de::DeserializeSeed
+#[cfg(feature = "serde")]
+pub struct LenientSeed<
+    K,
+    V,
+    #[cfg(feature = "std")] S = std::collections::hash_map::RandomState,
+    #[cfg(not(feature = "std"))] S,
+    A: Allocator = Global,
+    VS = PhantomData<V>,
+    F = FromSeq,
+>(ExtractMapSeed<K, V, S, A, VS, F>);
 
-            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
-                let size_hint = map.size_hint();
-                std::iter::from_fn(|| map.next_entry::<IgnoredAny, V>().transpose())
-                    .map(|res| res.map(|(_, v)| v))
-                    .with_size_hint(size_hint)
-                    .collect()
-            }
+#[cfg(feature = "serde")]
+impl<K, V, S, A, VS, F> Clone for LenientSeed<K, V, S, A, VS, F>
+where
+    S: Clone,
+    A: Allocator + Clone,
+    VS: Clone,
+{
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
 
-            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
-                let size_hint = seq.size_hint();
-                std::iter::from_fn(|| seq.next_element().transpose())
-                    .with_size_hint(size_hint)
-                    .collect()
-            }
-        }
+#[cfg(feature = "serde")]
+impl<K, V, S, A: Allocator, VS, F> Debug for LenientSeed<K, V, S, A, VS, F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("LenientSeed").field(&self.0).finish()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, K, V, S, A, VS> DeserializeSeed<'de> for LenientSeed<K, V, S, A, VS, FromSeq>
+where
+    K: Debug + Hash + Eq,
+    V: ComputeKey<K>,
+    S: BuildHasher,
+    A: Allocator,
+    VS: DeserializeSeed<'de, Value = V> + Clone,
+{
+    type Value = LenientMap<K, V, S, A>;
+
+    fn deserialize<D: serde::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        let seed = self.0;
+        let mut map =
+            ExtractMap::with_capacity_and_hasher_in(seed.capacity, seed.build_hasher, seed.alloc);
+        let mut errors = Vec::new();
+
+        deserializer.deserialize_seq(SeqVisitor {
+            map: &mut map,
+            policy: seed.duplicate_policy,
+            value_seed: seed.value_seed,
+            errors: Some(&mut errors),
+        })?;
+
+        Ok(LenientMap { map, errors })
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, K, V, S, A, VS> DeserializeSeed<'de> for LenientSeed<K, V, S, A, VS, FromMap>
+where
+    K: Debug + Hash + Eq + serde::Deserialize<'de>,
+    V: ComputeKey<K>,
+    S: BuildHasher,
+    A: Allocator,
+    VS: DeserializeSeed<'de, Value = V> + Clone,
+{
+    type Value = LenientMap<K, V, S, A>;
+
+    fn deserialize<D: serde::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        let seed = self.0;
+        let mut map =
+            ExtractMap::with_capacity_and_hasher_in(seed.capacity, seed.build_hasher, seed.alloc);
+        let mut errors = Vec::new();
+
+        deserializer.deserialize_map(MapVisitor {
+            map: &mut map,
+            strict: false,
+            policy: seed.duplicate_policy,
+            value_seed: seed.value_seed,
+            errors: Some(&mut errors),
+        })?;
+
+        Ok(LenientMap { map, errors })
+    }
+}
+
+/// Deserializes an [`ExtractMap`] from a sequence of the values.
+///
+/// This uses [`serde::Deserializer::deserialize_seq`], so works with formats which are not self-describing.
+/// To deserialize from a map, use [`serde_as_map`].
+///
+/// # Example
+/// ```
+/// use extract_map::ExtractMap;
+/// # use extract_map::doc_examples::User;
+///
+/// let json = r#"[{"id": 0, "name": "Elliott"}, {"id": 1, "name": "Daisy"}]"#;
+/// let map: ExtractMap<u64, User> = serde_json::from_str(json).unwrap();
+///
+/// assert_eq!(map.get(&1), Some(&User { id: 1, name: "Daisy" }));
+///
+/// let bytes = postcard::to_allocvec(&map).unwrap();
+/// let round_tripped: ExtractMap<u64, User> = postcard::from_bytes(&bytes).unwrap();
+///
+/// assert_eq!(map, round_tripped);
+///
+/// // The length of the input is only trusted up to a limit, so a huge length errors instead of aborting.
+/// let bytes = [0xff, 0xff, 0xff, 0xff, 0x0f];
+/// assert!(postcard::from_bytes::<ExtractMap<u64, User>>(&bytes).is_err());
+/// ```
+///
+/// [`Deserialize::deserialize_in_place`](serde::Deserialize::deserialize_in_place) is also implemented, which clears
+/// the existing map and refills it, keeping its capacity and hasher. Like [`Deserialize::deserialize`], this only
+/// accepts a sequence, so payloads from [`serialize_as_map`] must instead be deserialized with
+/// [`serde_as_map`], which always creates a new map.
+///
+/// ```
+/// use extract_map::ExtractMap;
+/// use serde::Deserialize;
+/// # use extract_map::doc_examples::User;
+///
+/// let mut map: ExtractMap<u64, User> = ExtractMap::with_capacity(64);
+/// let capacity = map.capacity();
+///
+/// let json = r#"[{"id": 0, "name": "Elliott"}, {"id": 1, "name": "Daisy"}]"#;
+/// ExtractMap::deserialize_in_place(&mut serde_json::Deserializer::from_str(json), &mut map).unwrap();
+/// assert_eq!(map.len(), 2);
+///
+/// let json = r#"[{"id": 2, "name": "Cat"}]"#;
+/// ExtractMap::deserialize_in_place(&mut serde_json::Deserializer::from_str(json), &mut map).unwrap();
+/// assert_eq!(map.len(), 1);
+/// assert_eq!(map.get(&2).unwrap().name, "Cat");
+/// assert_eq!(map.capacity(), capacity);
+/// ```
+///
+/// [`Deserialize::deserialize`]: serde::Deserialize::deserialize
+#[cfg(feature = "serde")]
+impl<'de, K, V, S, A> serde::Deserialize<'de> for ExtractMap<K, V, S, A>
+where
+    K: Hash + Eq,
+    V: ComputeKey<K> + serde::Deserialize<'de>,
+    S: BuildHasher + Default,
+    A: Allocator + Default,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut map = Self::default();
+        Self::deserialize_in_place(deserializer, &mut map)?;
+        Ok(map)
+    }
 
-        deserializer.deserialize_any(Visitor(PhantomData))
+    fn deserialize_in_place<D: serde::Deserializer<'de>>(
+        deserializer: D,
+        place: &mut Self,
+    ) -> Result<(), D::Error> {
+        place.clear();
+
+        let visitor = SeqVisitor {
+            map: place,
+            policy: Replace,
+            value_seed: PhantomData,
+            errors: None,
+        };
+
+        deserializer.deserialize_seq(visitor)
     }
 }
 
 /// Serializes an [`ExtractMap`] into a sequence of the values.
 #[cfg(feature = "serde")]
-impl<K, V: serde::Serialize, H> serde::Serialize for ExtractMap<K, V, H> {
+impl<K, V: serde::Serialize, H, A: Allocator> serde::Serialize for ExtractMap<K, V, H, A> {
     fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
         serializer.collect_seq(self)
     }
@@ -103,16 +845,349 @@ impl<K, V: serde::Serialize, H> serde::Serialize for ExtractMap<K, V, H> {
 
 /// A serialize method to serialize a [`ExtractMap`] to a map instead of a sequence.
 ///
-/// This should be used via serde's `serialize_with` field attribute.
+/// This should be used via serde's `serialize_with` field attribute, or via [`serde_as_map`]
+/// to also deserialize from a map.
 ///
 /// # Errors
 /// Errors if the underlying key or value serialisation fails.
 #[cfg(feature = "serde")]
-pub fn serialize_as_map<K, V, H, S>(map: &ExtractMap<K, V, H>, ser: S) -> Result<S::Ok, S::Error>
+pub fn serialize_as_map<K, V, H, A, S>(
+    map: &ExtractMap<K, V, H, A>,
+    ser: S,
+) -> Result<S::Ok, S::Error>
 where
+    A: Allocator,
     K: serde::Serialize + Hash + Eq,
-    V: serde::Serialize + ExtractKey<K>,
+    V: serde::Serialize + ComputeKey<K>,
+    S: serde::Serializer,
+{
+    use serde::ser::SerializeMap as _;
+
+    let mut ser_map = ser.serialize_map(Some(map.len()))?;
+    for value in map {
+        ser_map.serialize_entry(Borrow::<K>::borrow(&value.compute_key()), value)?;
+    }
+
+    ser_map.end()
+}
+
+/// Collects the values of an [`ExtractMap`], sorted by their keys.
+fn sorted_values<K, V, H, A>(map: &ExtractMap<K, V, H, A>) -> Vec<&V>
+where
+    A: Allocator,
+    K: Ord + Hash + Eq,
+    V: ComputeKey<K>,
+{
+    let mut values: Vec<&V> = map.iter().collect();
+    values.sort_unstable_by(|a, b| {
+        let (a, b) = (a.compute_key(), b.compute_key());
+        Borrow::<K>::borrow(&a).cmp(b.borrow())
+    });
+
+    values
+}
+
+/// A serialize method to serialize a [`ExtractMap`] to a sequence of the values, sorted by their keys.
+///
+/// This produces the same format as the [`serde::Serialize`] implementation, but with a stable order, and
+/// should be used via serde's `serialize_with` field attribute.
+///
+/// # Example
+/// ```
+/// use extract_map::ExtractMap;
+/// # use extract_map::doc_examples::User;
+///
+/// #[derive(serde::Serialize)]
+/// struct Users {
+///     #[serde(serialize_with = "extract_map::serialize_sorted")]
+///     users: ExtractMap<u64, User>,
+/// }
+///
+/// let users = Users {
+///     users: [User { id: 2, name: "Cat" }, User { id: 0, name: "Elliott" }, User { id: 1, name: "Daisy" }]
+///         .into_iter()
+///         .collect(),
+/// };
+///
+/// assert_eq!(
+///     serde_json::to_string(&users).unwrap(),
+///     r#"{"users":[{"id":0,"name":"Elliott"},{"id":1,"name":"Daisy"},{"id":2,"name":"Cat"}]}"#,
+/// );
+/// ```
+///
+/// # Errors
+/// Errors if the underlying value serialisation fails.
+#[cfg(feature = "serde")]
+pub fn serialize_sorted<K, V, H, A, S>(
+    map: &ExtractMap<K, V, H, A>,
+    ser: S,
+) -> Result<S::Ok, S::Error>
+where
+    A: Allocator,
+    K: Ord + Hash + Eq,
+    V: serde::Serialize + ComputeKey<K>,
+    S: serde::Serializer,
+{
+    ser.collect_seq(sorted_values(map))
+}
+
+/// A serialize method to serialize a [`ExtractMap`] to a map, sorted by the keys.
+///
+/// This produces the same format as [`serialize_as_map`], but with a stable order, and should be used via
+/// serde's `serialize_with` field attribute.
+///
+/// # Example
+/// ```
+/// use extract_map::ExtractMap;
+/// # use extract_map::doc_examples::User;
+///
+/// #[derive(serde::Serialize)]
+/// struct Users {
+///     #[serde(serialize_with = "extract_map::serialize_as_sorted_map")]
+///     users: ExtractMap<u64, User>,
+/// }
+///
+/// let users = Users {
+///     users: [User { id: 1, name: "Daisy" }, User { id: 0, name: "Elliott" }].into_iter().collect(),
+/// };
+///
+/// assert_eq!(
+///     serde_json::to_string(&users).unwrap(),
+///     r#"{"users":{"0":{"id":0,"name":"Elliott"},"1":{"id":1,"name":"Daisy"}}}"#,
+/// );
+/// ```
+///
+/// # Errors
+/// Errors if the underlying key or value serialisation fails.
+#[cfg(feature = "serde")]
+pub fn serialize_as_sorted_map<K, V, H, A, S>(
+    map: &ExtractMap<K, V, H, A>,
+    ser: S,
+) -> Result<S::Ok, S::Error>
+where
+    A: Allocator,
+    K: serde::Serialize + Ord + Hash + Eq,
+    V: serde::Serialize + ComputeKey<K>,
     S: serde::Serializer,
 {
-    ser.collect_map(map.iter().map(|v| (v.extract_key(), v)))
+    use serde::ser::SerializeMap as _;
+
+    let mut ser_map = ser.serialize_map(Some(map.len()))?;
+    for value in sorted_values(map) {
+        ser_map.serialize_entry(Borrow::<K>::borrow(&value.compute_key()), value)?;
+    }
+
+    ser_map.end()
+}
+
+/// Serializes and deserializes an [`ExtractMap`] as a sequence of the values, for use with `#[serde(with = "...")]`.
+///
+/// This is the same as the [`serde::Serialize`] and [`serde::Deserialize`] implementations on [`ExtractMap`],
+/// and works with formats which are not self-describing.
+///
+/// # Example
+/// ```
+/// use extract_map::ExtractMap;
+/// # use extract_map::doc_examples::User;
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct Users {
+///     #[serde(with = "extract_map::serde_as_seq")]
+///     users: ExtractMap<u64, User>,
+/// }
+///
+/// let users = Users { users: [User { id: 0, name: "Elliott" }].into_iter().collect() };
+///
+/// let bytes = postcard::to_allocvec(&users).unwrap();
+/// let round_tripped: Users = postcard::from_bytes(&bytes).unwrap();
+///
+/// assert_eq!(users.users, round_tripped.users);
+/// ```
+#[cfg(feature = "serde")]
+pub mod serde_as_seq {
+    use core::hash::{BuildHasher, Hash};
+
+    use allocator_api2::alloc::Allocator;
+
+    use crate::{ComputeKey, ExtractMap};
+
+    /// Serializes an [`ExtractMap`] into a sequence of the values.
+    ///
+    /// # Errors
+    /// Errors if the underlying value serialisation fails.
+    pub fn serialize<K, V, H, A, S>(map: &ExtractMap<K, V, H, A>, ser: S) -> Result<S::Ok, S::Error>
+    where
+        V: serde::Serialize,
+        A: Allocator,
+        S: serde::Serializer,
+    {
+        ser.collect_seq(map)
+    }
+
+    /// Deserializes an [`ExtractMap`] from a sequence of the values.
+    ///
+    /// # Errors
+    /// Errors if the input is not a sequence, or the underlying value deserialisation fails.
+    pub fn deserialize<'de, K, V, H, A, D>(de: D) -> Result<ExtractMap<K, V, H, A>, D::Error>
+    where
+        K: Hash + Eq,
+        V: ComputeKey<K> + serde::Deserialize<'de>,
+        H: BuildHasher + Default,
+        A: Allocator + Default,
+        D: serde::Deserializer<'de>,
+    {
+        serde::Deserialize::deserialize(de)
+    }
+}
+
+/// Serializes and deserializes an [`ExtractMap`] as a map from the keys to the values, for use with `#[serde(with = "...")]`.
+///
+/// The keys are deserialized and then discarded, as they are extracted from the values, so this works with formats
+/// which are not self-describing.
+///
+/// # Example
+/// ```
+/// use extract_map::ExtractMap;
+/// # use extract_map::doc_examples::User;
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct Users {
+///     #[serde(with = "extract_map::serde_as_map")]
+///     users: ExtractMap<u64, User>,
+/// }
+///
+/// let json = r#"{"users": {"0": {"id": 0, "name": "Elliott"}, "1": {"id": 1, "name": "Daisy"}}}"#;
+/// let users: Users = serde_json::from_str(json).unwrap();
+///
+/// assert_eq!(users.users.get(&0), Some(&User { id: 0, name: "Elliott" }));
+///
+/// let bytes = postcard::to_allocvec(&users).unwrap();
+/// let round_tripped: Users = postcard::from_bytes(&bytes).unwrap();
+///
+/// assert_eq!(users.users, round_tripped.users);
+/// ```
+#[cfg(feature = "serde")]
+pub mod serde_as_map {
+    use core::{
+        hash::{BuildHasher, Hash},
+        marker::PhantomData,
+    };
+
+    use allocator_api2::alloc::Allocator;
+
+    use super::{MapVisitor, Replace};
+    use crate::{ComputeKey, ExtractMap};
+
+    /// Serializes an [`ExtractMap`] into a map from the keys to the values.
+    ///
+    /// # Errors
+    /// Errors if the underlying key or value serialisation fails.
+    pub fn serialize<K, V, H, A, S>(map: &ExtractMap<K, V, H, A>, ser: S) -> Result<S::Ok, S::Error>
+    where
+        K: serde::Serialize + Hash + Eq,
+        V: serde::Serialize + ComputeKey<K>,
+        A: Allocator,
+        S: serde::Serializer,
+    {
+        super::serialize_as_map(map, ser)
+    }
+
+    /// Deserializes an [`ExtractMap`] from a map, discarding the keys.
+    ///
+    /// # Errors
+    /// Errors if the input is not a map, or the underlying key or value deserialisation fails.
+    pub fn deserialize<'de, K, V, H, A, D>(de: D) -> Result<ExtractMap<K, V, H, A>, D::Error>
+    where
+        K: Hash + Eq + serde::Deserialize<'de>,
+        V: ComputeKey<K> + serde::Deserialize<'de>,
+        H: BuildHasher + Default,
+        A: Allocator + Default,
+        D: serde::Deserializer<'de>,
+    {
+        let mut map = ExtractMap::default();
+        de.deserialize_map(MapVisitor {
+            map: &mut map,
+            strict: false,
+            policy: Replace,
+            value_seed: PhantomData,
+            errors: None,
+        })?;
+
+        Ok(map)
+    }
+}
+
+/// Serializes and deserializes an [`ExtractMap`] as a map from the keys to the values, checking the keys when
+/// deserializing.
+///
+/// This is the same as [`serde_as_map`](crate::serde_as_map), except deserialization errors if the key of an entry
+/// does not match the key of its value, instead of silently using the key of the value.
+///
+/// # Example
+/// ```
+/// use extract_map::ExtractMap;
+/// # use extract_map::doc_examples::User;
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct Users {
+///     #[serde(with = "extract_map::serde_as_strict_map")]
+///     users: ExtractMap<u64, User>,
+/// }
+///
+/// let json = r#"{"users": {"0": {"id": 0, "name": "Elliott"}, "1": {"id": 1, "name": "Daisy"}}}"#;
+/// assert!(serde_json::from_str::<Users>(json).is_ok());
+///
+/// let json = r#"{"users": {"5": {"id": 1, "name": "Daisy"}}}"#;
+/// assert!(serde_json::from_str::<Users>(json).is_err());
+/// ```
+#[cfg(feature = "serde")]
+pub mod serde_as_strict_map {
+    use core::{
+        hash::{BuildHasher, Hash},
+        marker::PhantomData,
+    };
+
+    use allocator_api2::alloc::Allocator;
+
+    use super::{MapVisitor, Replace};
+    use crate::{ComputeKey, ExtractMap};
+
+    /// Serializes an [`ExtractMap`] into a map from the keys to the values.
+    ///
+    /// # Errors
+    /// Errors if the underlying key or value serialisation fails.
+    pub fn serialize<K, V, H, A, S>(map: &ExtractMap<K, V, H, A>, ser: S) -> Result<S::Ok, S::Error>
+    where
+        K: serde::Serialize + Hash + Eq,
+        V: serde::Serialize + ComputeKey<K>,
+        A: Allocator,
+        S: serde::Serializer,
+    {
+        super::serialize_as_map(map, ser)
+    }
+
+    /// Deserializes an [`ExtractMap`] from a map, checking each key matches the key of its value.
+    ///
+    /// # Errors
+    /// Errors if the input is not a map, the underlying key or value deserialisation fails, or
+    /// a key does not match the key of its value.
+    pub fn deserialize<'de, K, V, H, A, D>(de: D) -> Result<ExtractMap<K, V, H, A>, D::Error>
+    where
+        K: Hash + Eq + serde::Deserialize<'de>,
+        V: ComputeKey<K> + serde::Deserialize<'de>,
+        H: BuildHasher + Default,
+        A: Allocator + Default,
+        D: serde::Deserializer<'de>,
+    {
+        let mut map = ExtractMap::default();
+        de.deserialize_map(MapVisitor {
+            map: &mut map,
+            strict: true,
+            policy: Replace,
+            value_seed: PhantomData,
+            errors: None,
+        })?;
+
+        Ok(map)
+    }
 }
@@ -0,0 +1,244 @@
+//! A buffer for any self-describing value, so a value which fails to deserialize can be skipped.
+//!
+//! This is a minimal version of serde's private `Content` type, which is not public API.
+
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::marker::PhantomData;
+
+use serde::de::{
+    self,
+    value::{MapAccessDeserializer, MapDeserializer, SeqDeserializer},
+    IntoDeserializer as _, MapAccess, SeqAccess,
+};
+use serde::Deserialize as _;
+
+use super::MAX_PREALLOCATION;
+
+pub(super) enum Content<'de> {
+    Bool(bool),
+    U64(u64),
+    I64(i64),
+    U128(u128),
+    I128(i128),
+    F64(f64),
+    Char(char),
+    String(String),
+    Str(&'de str),
+    ByteBuf(Vec<u8>),
+    Bytes(&'de [u8]),
+    None,
+    Some(Box<Content<'de>>),
+    Unit,
+    Newtype(Box<Content<'de>>),
+    Seq(Vec<Content<'de>>),
+    Map(Vec<(Content<'de>, Content<'de>)>),
+}
+
+impl<'de> serde::Deserialize<'de> for Content<'de> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ContentVisitor)
+    }
+}
+
+struct ContentVisitor;
+
+impl<'de> de::Visitor<'de> for ContentVisitor {
+    type Value = Content<'de>;
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        formatter.write_str("any value")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Self::Value, E> {
+        Ok(Content::Bool(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(Content::I64(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(Content::U64(v))
+    }
+
+    fn visit_i128<E: de::Error>(self, v: i128) -> Result<Self::Value, E> {
+        Ok(Content::I128(v))
+    }
+
+    fn visit_u128<E: de::Error>(self, v: u128) -> Result<Self::Value, E> {
+        Ok(Content::U128(v))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+        Ok(Content::F64(v))
+    }
+
+    fn visit_char<E: de::Error>(self, v: char) -> Result<Self::Value, E> {
+        Ok(Content::Char(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(Content::String(v.into()))
+    }
+
+    fn visit_borrowed_str<E: de::Error>(self, v: &'de str) -> Result<Self::Value, E> {
+        Ok(Content::Str(v))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+        Ok(Content::String(v))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        Ok(Content::ByteBuf(v.into()))
+    }
+
+    fn visit_borrowed_bytes<E: de::Error>(self, v: &'de [u8]) -> Result<Self::Value, E> {
+        Ok(Content::Bytes(v))
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(Content::ByteBuf(v))
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(Content::None)
+    }
+
+    fn visit_some<D: serde::Deserializer<'de>>(self, d: D) -> Result<Self::Value, D::Error> {
+        Content::deserialize(d).map(|content| Content::Some(Box::new(content)))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(Content::Unit)
+    }
+
+    fn visit_newtype_struct<D: serde::Deserializer<'de>>(
+        self,
+        d: D,
+    ) -> Result<Self::Value, D::Error> {
+        Content::deserialize(d).map(|content| Content::Newtype(Box::new(content)))
+    }
+
+    fn visit_seq<M: SeqAccess<'de>>(self, mut seq: M) -> Result<Self::Value, M::Error> {
+        let capacity = seq.size_hint().unwrap_or_default().min(MAX_PREALLOCATION);
+
+        let mut elements = Vec::with_capacity(capacity);
+        while let Some(element) = seq.next_element()? {
+            elements.push(element);
+        }
+
+        Ok(Content::Seq(elements))
+    }
+
+    fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> Result<Self::Value, M::Error> {
+        let capacity = map.size_hint().unwrap_or_default().min(MAX_PREALLOCATION);
+
+        let mut entries = Vec::with_capacity(capacity);
+        while let Some(entry) = map.next_entry()? {
+            entries.push(entry);
+        }
+
+        Ok(Content::Map(entries))
+    }
+}
+
+/// Deserializes from a buffered [`Content`], producing errors of type `E`.
+pub(super) struct ContentDeserializer<'de, E> {
+    content: Content<'de>,
+    phantom: PhantomData<E>,
+}
+
+impl<'de, E> ContentDeserializer<'de, E> {
+    pub(super) fn new(content: Content<'de>) -> Self {
+        Self {
+            content,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<'de, E: de::Error> de::IntoDeserializer<'de, E> for Content<'de> {
+    type Deserializer = ContentDeserializer<'de, E>;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        ContentDeserializer::new(self)
+    }
+}
+
+impl<'de, E: de::Error> serde::Deserializer<'de> for ContentDeserializer<'de, E> {
+    type Error = E;
+
+    fn deserialize_any<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, E> {
+        match self.content {
+            Content::Bool(v) => visitor.visit_bool(v),
+            Content::U64(v) => visitor.visit_u64(v),
+            Content::I64(v) => visitor.visit_i64(v),
+            Content::U128(v) => visitor.visit_u128(v),
+            Content::I128(v) => visitor.visit_i128(v),
+            Content::F64(v) => visitor.visit_f64(v),
+            Content::Char(v) => visitor.visit_char(v),
+            Content::String(v) => visitor.visit_string(v),
+            Content::Str(v) => visitor.visit_borrowed_str(v),
+            Content::ByteBuf(v) => visitor.visit_byte_buf(v),
+            Content::Bytes(v) => visitor.visit_borrowed_bytes(v),
+            Content::None => visitor.visit_none(),
+            Content::Some(v) => visitor.visit_some(ContentDeserializer::new(*v)),
+            Content::Unit => visitor.visit_unit(),
+            Content::Newtype(v) => visitor.visit_newtype_struct(ContentDeserializer::new(*v)),
+            Content::Seq(v) => {
+                let mut seq = SeqDeserializer::new(v.into_iter());
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            Content::Map(v) => {
+                let mut map = MapDeserializer::new(v.into_iter());
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+        }
+    }
+
+    fn deserialize_option<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, E> {
+        match self.content {
+            Content::None | Content::Unit => visitor.visit_none(),
+            Content::Some(v) => visitor.visit_some(ContentDeserializer::new(*v)),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, E> {
+        match self.content {
+            Content::Newtype(v) => visitor.visit_newtype_struct(ContentDeserializer::new(*v)),
+            _ => visitor.visit_newtype_struct(self),
+        }
+    }
+
+    fn deserialize_enum<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, E> {
+        match self.content {
+            // A unit variant is represented by its name, and other variants by a map with a single entry.
+            Content::String(v) => visitor.visit_enum(v.into_deserializer()),
+            Content::Str(v) => visitor.visit_enum(v.into_deserializer()),
+            Content::Map(v) if v.len() == 1 => visitor.visit_enum(MapAccessDeserializer::new(
+                MapDeserializer::new(v.into_iter()),
+            )),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
@@ -1,10 +1,13 @@
 use typesize::{if_typesize_details, TypeSize};
 
+use allocator_api2::alloc::Allocator;
+
 use crate::ExtractMap;
 
-impl<K, V: TypeSize, S: TypeSize> TypeSize for ExtractMap<K, V, S> {
+impl<K, V: TypeSize, S: TypeSize, A: Allocator> TypeSize for ExtractMap<K, V, S, A> {
     fn extra_size(&self) -> usize {
-        self.table.extra_size() + self.build_hasher.extra_size()
+        let values_size: usize = self.table.iter().map(V::extra_size).sum();
+        self.table.allocation_size() + values_size + self.build_hasher.extra_size()
     }
 
     if_typesize_details! {
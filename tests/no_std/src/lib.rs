@@ -0,0 +1,54 @@
+//! Checks that `extract_map` builds and works without `std`.
+//!
+//! This is built for a target without `std` in CI, such as `thumbv7m-none-eabi`.
+#![no_std]
+
+extern crate alloc;
+
+use core::hash::{BuildHasherDefault, Hasher};
+
+use extract_map::{ExtractKey, ExtractMap};
+
+/// A minimal FNV-1a hasher, as `RandomState` is not available without `std`.
+#[derive(Default)]
+pub struct FnvHasher(u64);
+
+impl Hasher for FnvHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+pub type FnvBuildHasher = BuildHasherDefault<FnvHasher>;
+
+pub struct Sensor {
+    pub id: u16,
+    pub reading: i32,
+}
+
+impl ExtractKey<u16> for Sensor {
+    fn extract_key(&self) -> &u16 {
+        &self.id
+    }
+}
+
+#[must_use]
+pub fn total_reading() -> i32 {
+    let mut map: ExtractMap<u16, Sensor, FnvBuildHasher> = ExtractMap::default();
+    map.insert(Sensor { id: 1, reading: 10 });
+    map.insert(Sensor { id: 2, reading: 20 });
+
+    if let Some(mut sensor) = map.get_mut(&1) {
+        sensor.reading += 5;
+    }
+
+    map.retain(|sensor| sensor.reading > 0);
+    map.iter().map(|sensor| sensor.reading).sum()
+}